@@ -0,0 +1,68 @@
+use ndarray::Array2;
+
+/// Colours of the [`TERRAIN_BANDS`](crate::TERRAIN_BANDS) classes for
+/// [`write_indexed_png`](crate::write_indexed_png), close to those [`ColorRamp::terrain`] gives
+/// the same heights.
+pub const TERRAIN_PALETTE: [[u8; 3]; 7] = [
+    [8, 24, 88],
+    [48, 108, 180],
+    [216, 200, 144],
+    [88, 160, 64],
+    [40, 104, 40],
+    [120, 96, 72],
+    [232, 232, 232],
+];
+
+/// A gradient mapping heights to colours, linearly interpolated between stops.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ColorRamp {
+    stops: Vec<(f32, [u8; 3])>,
+}
+
+impl ColorRamp {
+    /// A ramp through `(height, rgb)` stops, in any order. Heights below the lowest stop or above
+    /// the highest take that stop's colour.
+    ///
+    /// Panics if `stops` is empty.
+    pub fn new(mut stops: Vec<(f32, [u8; 3])>) -> Self {
+        assert!(!stops.is_empty(), "a colour ramp needs at least one stop");
+        stops.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Self { stops }
+    }
+
+    /// Black at `-1` to white at `1`.
+    pub fn grayscale() -> Self {
+        Self::new(vec![(-1., [0, 0, 0]), (1., [255, 255, 255])])
+    }
+
+    /// Deep water through shallows, beach, grass, forest and rock to snow, with the shoreline at
+    /// height 0.
+    pub fn terrain() -> Self {
+        Self::new(vec![
+            (-1., [8, 24, 88]),
+            (-0.25, [24, 72, 160]),
+            (0., [72, 144, 200]),
+            (0.03, [216, 200, 144]),
+            (0.1, [88, 160, 64]),
+            (0.35, [40, 104, 40]),
+            (0.6, [120, 96, 72]),
+            (0.85, [232, 232, 232]),
+        ])
+    }
+
+    pub fn color(&self, h: f32) -> [u8; 3] {
+        let k = self.stops.partition_point(|&(s, _)| s <= h);
+        if k == 0 { return self.stops[0].1; }
+        if k == self.stops.len() { return self.stops[k - 1].1; }
+
+        let (h0, c0) = self.stops[k - 1];
+        let (h1, c1) = self.stops[k];
+        let w = (h - h0) / (h1 - h0);
+        [0, 1, 2].map(|c| (c0[c] as f32 + (c1[c] as f32 - c0[c] as f32) * w).round() as u8)
+    }
+
+    /// Colours every pixel of `map`.
+    pub fn apply(&self, map: &Array2<f32>) -> Array2<[u8; 3]> {
+        map.map(|&h| self.color(h))
+    }
+}
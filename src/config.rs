@@ -0,0 +1,927 @@
+use std::{error::Error, fmt};
+
+use ndarray::Array2;
+use rand::RngCore;
+
+use crate::{
+    generate::{self, Source}, Curve, Fade, GradientGrids, GradientSource, GradientsError, HydraulicParams, Invert,
+    NoiseKind, NoiseStyle, Normalization,
+};
+
+/// Parameters for generating a heightmap.
+///
+/// Start from [`HeightmapConfig::default`] and override what you need with the `with_*` setters:
+///
+/// ```
+/// use heightmap_gen::HeightmapConfig;
+///
+/// let map = HeightmapConfig::default().with_size(256, 128).with_octaves(6).generate();
+/// assert_eq!(map.dim(), (128, 256));
+/// ```
+///
+/// With the `config-file` feature, on by default, configs can also be deserialized with serde,
+/// as the command line's `--config` does from TOML and JSON files. Fields go by their names
+/// here and enum variants by theirs in kebab-case, so `normalization = "min-max"` or
+/// `noise = { worley = "f2-minus-f1" }`; missing fields keep their defaults, and unknown ones are
+/// an error, so a misspelt setting isn't silently ignored. Seed words may also be strings of
+/// decimal or `0x`-prefixed hex digits, since TOML integers stop at `i64::MAX`. Custom fade
+/// curves and region masks can't be deserialized.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "config-file", derive(serde::Deserialize), serde(default, deny_unknown_fields))]
+pub struct HeightmapConfig {
+    pub width: usize,
+    pub height: usize,
+    /// Number of noise octaves; octave `level` has gradient cells `lacunarity^level` pixels wide.
+    pub octaves: usize,
+    /// Finest octave level that is generated; levels below it are skipped.
+    ///
+    /// Every octave's gradient grid is allocated before any pixel is filled, and one at level
+    /// `L` holds about `(width >> L) * (height >> L)` gradients of 8 bytes each (at the default
+    /// lacunarity), so level 0 alone takes twice the memory of the heightmap. Raising this to 1
+    /// or 2 cuts gradient memory by 4 or 16 times and skips the octaves that look most like plain
+    /// noise. Skipping levels
+    /// doesn't change the gradients of the others.
+    pub finest_level: usize,
+    /// Coarsest octave level that is generated, if not `octaves - 1`; levels above it are
+    /// skipped.
+    ///
+    /// The kept octaves keep the amplitudes and gradients they have in the full map, and
+    /// [`Normalization::ScaleSum`] divides by the sum of just those, so together with
+    /// `finest_level` this picks out a band of detail to layer over terrain made elsewhere.
+    pub coarsest_level: Option<usize>,
+    /// Smallest gradient cells generated, in pixels along the narrower side of a cell once
+    /// `feature_scale` is applied; finer levels are skipped like those below `finest_level`,
+    /// however many octaves there are.
+    ///
+    /// The finest octaves are the slowest and the most like plain noise, so a cap of 4 or so
+    /// speeds up large renders a lot and often looks better. The coarser octaves are unchanged.
+    pub detail_cap: Option<f32>,
+    /// Fraction of amplitude lost from each octave to the next finer one.
+    ///
+    /// The same falloff can be set as a Hurst exponent with
+    /// [`with_hurst`](HeightmapConfig::with_hurst); the default is a Hurst exponent of about
+    /// 1.58.
+    pub attenuation: f32,
+    /// Amplitude of each octave, coarsest first, in place of the geometric schedule
+    /// `attenuation` gives. Octaves past the end of the list get no weight, and
+    /// [`Normalization::ScaleSum`] divides by the weights actually used.
+    pub octave_weights: Option<Vec<f32>>,
+    /// Factor cell size shrinks by from each octave to the next finer one. Values other than 2
+    /// keep the lattices of different octaves from lining up, which hides some of the
+    /// self-similarity of the default. Must be above 1, so coarser octaves have larger cells.
+    pub lacunarity: f32,
+    /// Weight of the noise against the squircle falloff in the final blend.
+    pub perlin_weight: f32,
+    /// Seed pair for the gradient RNG.
+    ///
+    /// The same seed and settings give the same map on any machine and thread count, with two
+    /// caveats: gradient angles go through `f32::sin_cos`, which comes from the platform's maths
+    /// library and may differ in the last bit between targets, and Rust doesn't promise that
+    /// `powf` and `hypot` (used by the island falloff and slopes) round the same everywhere
+    /// either. Builds with and without the `f64` feature also differ slightly.
+    #[cfg_attr(feature = "config-file", serde(deserialize_with = "deserialize_seed"))]
+    pub seed: (u64, u64),
+    /// Stretch applied to feature size along the (horizontal, vertical) axes.
+    ///
+    /// Cell sizes are measured in pixels, so features are square at `(1., 1.)` whatever the
+    /// aspect ratio of the map; `(2., 1.)` makes them twice as wide as they are tall.
+    pub feature_scale: (f32, f32),
+    /// `(angle, aspect)` of an anisotropic stretch, if any: features are made `aspect` times as
+    /// long along the direction `angle` radians from the x axis towards the y axis (clockwise on
+    /// screen, since rows run down), for dunes or ridges lined up by wind or tectonics. An
+    /// aspect below 1 squashes them instead, and at 1 the map is the same as without.
+    ///
+    /// Maps can only wrap around with an aspect of 1.
+    pub anisotropy: Option<(f32, f32)>,
+    /// Whether to turn each octave by its own angle, hashed from `seed` and the octave level, so
+    /// the axis-aligned structure of the lattices doesn't line up from one octave to the next.
+    /// Warp fields aren't turned, and maps can't wrap around with it set.
+    pub octave_rotation: bool,
+    pub noise: NoiseKind,
+    /// How gradient noise picks its gradients, and whether Worley feature points are hashed.
+    ///
+    /// By default each one is a function of its lattice position, the octave and `seed`, so
+    /// resizing a map keeps the noise where the old and new sizes overlap; the squircle falloff
+    /// and island still stretch to the new edges.
+    pub gradients: GradientSource,
+    /// Whether [`GradientSource::RandomAngles`] and [`GradientSource::Table`] draw each octave's
+    /// gradients from a sub-seed of its own rather than one stream shared by all of them, so
+    /// changing `octaves`, `finest_level` or `coarsest_level` leaves the other octaves'
+    /// gradients alone; their amplitudes still follow the octave count.
+    ///
+    /// Octave level `L` is drawn from an RNG seeded with `seed` with a hash of `seed`, `L` and a
+    /// fixed key XORed into the first word. Hashed gradients are per octave already, and
+    /// [`generate_with_rng`](Self::generate_with_rng) still draws the octaves from `seed` with it
+    /// set.
+    pub octave_seeds: bool,
+    /// Whether to round random and hashed gradient angles to 4096 directions looked up in a
+    /// table, instead of working out `sin_cos` for each; it speeds up filling large gradient
+    /// grids at an all but invisible cost in quality.
+    pub angle_table: bool,
+    pub style: NoiseStyle,
+    /// Interpolation curve between lattice points; only used by [`NoiseKind::Perlin`] and
+    /// [`NoiseKind::Value`].
+    pub fade: Fade,
+    /// How the summed octaves are scaled, before the squircle falloff and later stages.
+    pub normalization: Normalization,
+    /// Makes the noise wrap around between the left and right edges of the map, so copies of it
+    /// side by side join seamlessly, as for the longitude of a cylindrical world.
+    ///
+    /// Each octave's cell width is adjusted to fit a whole number of cells across the map. Only
+    /// [`NoiseKind::Perlin`] tiles, and the squircle falloff doesn't, so set `perlin_weight` to 1.
+    pub wrap_x: bool,
+    /// Makes the noise wrap around between the top and bottom edges of the map, like `wrap_x`
+    /// does horizontally.
+    pub wrap_y: bool,
+    /// Position of the map's top-left pixel in a larger world, as `(x, y)` in pixels.
+    ///
+    /// With [`GradientSource::Hashed`], maps generated at different origins are windows onto
+    /// the same noise, so a tile at `(width, 0)` continues the one at `(0, 0)`. Only the noise
+    /// lines up: set `perlin_weight` to 1, keep [`Normalization::ScaleSum`], and leave out the
+    /// bump, island falloff, warping and erosion, which all depend on the map's own edges. Simplex
+    /// noise's skewed lattice doesn't line up either. The origin is ignored with other gradient
+    /// sources and along wrapping axes.
+    pub origin: (i64, i64),
+    /// Exponent of a radial island falloff, if any.
+    ///
+    /// Heights are measured up from `-1` and multiplied by `1 - d^exponent`, where `d` is the
+    /// distance from the centre with the edge midpoints at 1, so everything outside that circle
+    /// sinks to `-1`. Larger exponents give flatter islands with more abrupt coastlines.
+    pub island: Option<f32>,
+    /// `(amplitude, power, centre)` of a radial bump added to the summed octaves before
+    /// normalization, if any, to raise a mountain (or, with a negative amplitude, sink a basin)
+    /// around `centre`, given as fractions `(x, y)` of the width and height.
+    ///
+    /// Heights are raised by `amplitude * (1 - d)^power`, where `d` is the distance from the
+    /// centre in halves of the width and height, so a bump in the middle reaches 0 at the edge
+    /// midpoints. The amplitude is in the units of [`Normalization::ScaleSum`] heights, whatever
+    /// the normalization. Larger powers give narrower peaks.
+    pub bump: Option<(f32, f32, (f32, f32))>,
+    /// `(margin, target)` of a fade around the edges of the map, if any, for standalone terrain
+    /// patches whose borders would otherwise stop abruptly.
+    ///
+    /// Within `margin` pixels of an edge, heights are blended towards `target`, such as the sea
+    /// level, along a half cosine, reaching it on the edge pixels; corners fade from both sides.
+    /// Unlike the radial `island` falloff, the frame is rectangular. Edges the map wraps around
+    /// at aren't faded, so it still tiles.
+    pub edge_fade: Option<(usize, f32)>,
+    /// `(levels, smoothness)` of terracing, if any.
+    ///
+    /// Heights are snapped to the nearest of `levels` evenly spaced steps from `-1` to `1`, then
+    /// blended back towards their original value by `smoothness`; at 0 the map has only those
+    /// `levels` distinct heights (before erosion).
+    pub terraces: Option<(u32, f32)>,
+    /// Domain warping strength, in pixels of displacement per unit of warp noise.
+    ///
+    /// The displacement comes from two extra noise fields with the coarsest octave's cell size,
+    /// drawn from a separate RNG stream; at 0 no warping happens.
+    pub warp_strength: f32,
+    /// `(iterations, talus)` of a [`thermal_erode`](crate::thermal_erode) pass run on the
+    /// finished map, if any.
+    pub thermal_erosion: Option<(usize, f32)>,
+    /// Droplet count and parameters of a [`hydraulic_erode`](crate::hydraulic_erode) pass run on
+    /// the finished map, after thermal erosion, if any. Droplets start at points drawn from a
+    /// separate RNG stream.
+    pub hydraulic_erosion: Option<(usize, HydraulicParams)>,
+    /// Factor applied to every height of the finished map, after erosion, to compress or expand
+    /// its vertical range.
+    pub gain: f32,
+    /// Offset added to every height after `gain`, to raise or sink the whole terrain.
+    ///
+    /// Quantized output formats clamp heights to `clamp_range`, so a large gain or bias flattens
+    /// peaks or valleys; [`HeightmapStats::clipped`](crate::HeightmapStats::clipped) counts the
+    /// pixels affected.
+    pub bias: f32,
+    /// Amplitude of white noise added to every pixel of the finished map, drawn from a separate
+    /// RNG stream; a little breaks up banding in 8-bit output. At 0 none is added.
+    pub grain: f32,
+    /// `(lo, hi)` heights that quantized output formats map onto their lowest and highest
+    /// samples, clamping heights outside them.
+    pub clamp_range: (f32, f32),
+    /// Where to flip the map upside down, if at all, for cave ceilings or sea floors from the
+    /// seed of the terrain above.
+    pub invert: Option<Invert>,
+    /// Response curve applied to the finished heights last of all, after gain, bias and
+    /// inversion, to reshape their spread within `clamp_range` before quantization.
+    pub curve: Option<Curve>,
+    /// Pixels to generate terrain on, if not the whole map, such as a continent outline; the
+    /// mask is indexed `[row, column]` and must be the size of the map.
+    ///
+    /// Pixels outside it skip the noise altogether and are set to `region_fill` once every other
+    /// stage is done, [`Pipeline`](crate::Pipeline) stages included, so sparse regions generate
+    /// quickly and landmasses from different seeds can be composited. [`Normalization::MinMax`]
+    /// and [`Normalization::Equalize`] only see the pixels inside, while erosion sees
+    /// `region_fill` around them. Detail added to a base map and volumes ignore the mask.
+    #[cfg_attr(feature = "config-file", serde(skip))]
+    pub region_mask: Option<Array2<bool>>,
+    /// Height of the pixels outside `region_mask`.
+    pub region_fill: f32,
+}
+
+impl Default for HeightmapConfig {
+    fn default() -> Self {
+        Self {
+            width: 512,
+            height: 512,
+            octaves: 8,
+            finest_level: 0,
+            coarsest_level: None,
+            detail_cap: None,
+            attenuation: 2. / 3.,
+            octave_weights: None,
+            lacunarity: 2.,
+            perlin_weight: 1. / 2.,
+            seed: (0x243F6A8885A308D3, 0x13198A2E03707344),
+            feature_scale: (1., 1.),
+            anisotropy: None,
+            octave_rotation: false,
+            octave_seeds: false,
+            noise: NoiseKind::Perlin,
+            gradients: GradientSource::Hashed,
+            angle_table: false,
+            style: NoiseStyle::Fbm,
+            fade: Fade::Cubic,
+            normalization: Normalization::ScaleSum,
+            wrap_x: false,
+            wrap_y: false,
+            origin: (0, 0),
+            island: None,
+            bump: None,
+            edge_fade: None,
+            terraces: None,
+            warp_strength: 0.,
+            thermal_erosion: None,
+            hydraulic_erosion: None,
+            gain: 1.,
+            bias: 0.,
+            grain: 0.,
+            clamp_range: (-1., 1.),
+            invert: None,
+            curve: None,
+            region_mask: None,
+            region_fill: -1.,
+        }
+    }
+}
+
+impl HeightmapConfig {
+    pub fn with_size(mut self, width: usize, height: usize) -> Self {
+        self.width = width;
+        self.height = height;
+        self
+    }
+
+    pub fn with_octaves(mut self, octaves: usize) -> Self {
+        self.octaves = octaves;
+        self
+    }
+
+    pub fn with_finest_level(mut self, finest_level: usize) -> Self {
+        self.finest_level = finest_level;
+        self
+    }
+
+    pub fn with_detail_cap(mut self, detail_cap: Option<f32>) -> Self {
+        self.detail_cap = detail_cap;
+        self
+    }
+
+    pub fn with_coarsest_level(mut self, coarsest_level: Option<usize>) -> Self {
+        self.coarsest_level = coarsest_level;
+        self
+    }
+
+    pub fn with_attenuation(mut self, attenuation: f32) -> Self {
+        self.attenuation = attenuation;
+        self
+    }
+
+    /// Sets `attenuation` from a Hurst exponent `h`, so each octave's amplitude is
+    /// `lacunarity^(-h)` times the previous one, for the current `lacunarity`. Higher values give
+    /// smoother terrain; 0 gives every octave the same amplitude.
+    pub fn with_hurst(mut self, h: f32) -> Self {
+        self.attenuation = attenuation_from_hurst(h, self.lacunarity);
+        self
+    }
+
+    pub fn with_octave_weights(mut self, weights: Option<Vec<f32>>) -> Self {
+        self.octave_weights = weights;
+        self
+    }
+
+    pub fn with_lacunarity(mut self, lacunarity: f32) -> Self {
+        self.lacunarity = lacunarity;
+        self
+    }
+
+    pub fn with_perlin_weight(mut self, perlin_weight: f32) -> Self {
+        self.perlin_weight = perlin_weight;
+        self
+    }
+
+    pub fn with_seed(mut self, seed: (u64, u64)) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    pub fn with_feature_scale(mut self, horizontal: f32, vertical: f32) -> Self {
+        self.feature_scale = (horizontal, vertical);
+        self
+    }
+
+    pub fn with_anisotropy(mut self, angle: f32, aspect: f32) -> Self {
+        self.anisotropy = Some((angle, aspect));
+        self
+    }
+
+    pub fn with_octave_rotation(mut self, octave_rotation: bool) -> Self {
+        self.octave_rotation = octave_rotation;
+        self
+    }
+
+    pub fn with_noise(mut self, noise: NoiseKind) -> Self {
+        self.noise = noise;
+        self
+    }
+
+    pub fn with_octave_seeds(mut self, octave_seeds: bool) -> Self {
+        self.octave_seeds = octave_seeds;
+        self
+    }
+
+    pub fn with_gradients(mut self, gradients: GradientSource) -> Self {
+        self.gradients = gradients;
+        self
+    }
+
+    pub fn with_angle_table(mut self, angle_table: bool) -> Self {
+        self.angle_table = angle_table;
+        self
+    }
+
+    pub fn with_style(mut self, style: NoiseStyle) -> Self {
+        self.style = style;
+        self
+    }
+
+    pub fn with_fade(mut self, fade: Fade) -> Self {
+        self.fade = fade;
+        self
+    }
+
+    pub fn with_normalization(mut self, normalization: Normalization) -> Self {
+        self.normalization = normalization;
+        self
+    }
+
+    /// Sets both `wrap_x` and `wrap_y`, for maps that tile in every direction.
+    pub fn with_tileable(mut self, tileable: bool) -> Self {
+        self.wrap_x = tileable;
+        self.wrap_y = tileable;
+        self
+    }
+
+    pub fn with_wrap(mut self, wrap_x: bool, wrap_y: bool) -> Self {
+        self.wrap_x = wrap_x;
+        self.wrap_y = wrap_y;
+        self
+    }
+
+    pub fn with_origin(mut self, x: i64, y: i64) -> Self {
+        self.origin = (x, y);
+        self
+    }
+
+    pub fn with_island(mut self, exponent: Option<f32>) -> Self {
+        self.island = exponent;
+        self
+    }
+
+    pub fn with_bump(mut self, bump: Option<(f32, f32, (f32, f32))>) -> Self {
+        self.bump = bump;
+        self
+    }
+
+    pub fn with_edge_fade(mut self, edge_fade: Option<(usize, f32)>) -> Self {
+        self.edge_fade = edge_fade;
+        self
+    }
+
+    pub fn with_terraces(mut self, levels: u32, smoothness: f32) -> Self {
+        self.terraces = Some((levels, smoothness));
+        self
+    }
+
+    pub fn with_warp_strength(mut self, warp_strength: f32) -> Self {
+        self.warp_strength = warp_strength;
+        self
+    }
+
+    pub fn with_thermal_erosion(mut self, iterations: usize, talus: f32) -> Self {
+        self.thermal_erosion = Some((iterations, talus));
+        self
+    }
+
+    pub fn with_hydraulic_erosion(mut self, droplets: usize, params: HydraulicParams) -> Self {
+        self.hydraulic_erosion = Some((droplets, params));
+        self
+    }
+
+    pub fn with_gain_bias(mut self, gain: f32, bias: f32) -> Self {
+        self.gain = gain;
+        self.bias = bias;
+        self
+    }
+
+    pub fn with_grain(mut self, grain: f32) -> Self {
+        self.grain = grain;
+        self
+    }
+
+    pub fn with_clamp_range(mut self, lo: f32, hi: f32) -> Self {
+        self.clamp_range = (lo, hi);
+        self
+    }
+
+    pub fn with_invert(mut self, invert: Option<Invert>) -> Self {
+        self.invert = invert;
+        self
+    }
+
+    pub fn with_curve(mut self, curve: Option<Curve>) -> Self {
+        self.curve = curve;
+        self
+    }
+
+    pub fn with_region_mask(mut self, mask: Option<Array2<bool>>, fill: f32) -> Self {
+        self.region_mask = mask;
+        self.region_fill = fill;
+        self
+    }
+
+    /// The Hurst exponent equivalent to `attenuation` at the current `lacunarity`.
+    pub fn hurst(&self) -> f32 {
+        hurst_from_attenuation(self.attenuation, self.lacunarity)
+    }
+
+    /// Gradient cell size of octave `level`, in pixels before `feature_scale`.
+    pub(crate) fn cell_size(&self, level: usize) -> f32 {
+        self.lacunarity.powi(level as i32)
+    }
+
+    /// The level and amplitude of each generated octave, coarsest first.
+    pub(crate) fn levels(&self) -> impl Iterator<Item = (usize, f32)> + '_ {
+        let mut scale = 1f32;
+        (self.finest().min(self.octaves)..self.octaves).rev().enumerate().map(move |(k, level)| {
+            let geometric = scale;
+            scale *= 1. - self.attenuation;
+            match &self.octave_weights {
+                Some(weights) => (level, weights.get(k).copied().unwrap_or(0.)),
+                None => (level, geometric),
+            }
+        }).filter(|&(level, _)| level <= self.coarsest())
+    }
+
+    /// The finest level that is generated, raised by `detail_cap` from `finest_level` to the
+    /// first with large enough cells, or to `octaves` if none has.
+    pub(crate) fn finest(&self) -> usize {
+        let Some(cap) = self.detail_cap else { return self.finest_level };
+        let narrowest = self.feature_scale.0.min(self.feature_scale.1);
+        (self.finest_level..self.octaves).find(|&level| self.cell_size(level) * narrowest >= cap)
+            .unwrap_or(self.octaves)
+    }
+
+    /// The coarsest level that is generated, or `usize::MAX` if none above it is skipped.
+    pub(crate) fn coarsest(&self) -> usize {
+        self.coarsest_level.unwrap_or(usize::MAX)
+    }
+
+    /// Checks that the settings describe a map that can be generated. Every `generate` method
+    /// calls this first and panics with the error if it fails.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.width == 0 || self.height == 0 {
+            return Err(ConfigError::EmptyMap { width: self.width, height: self.height });
+        }
+        if self.finest_level >= self.octaves {
+            return Err(ConfigError::NoOctaves { octaves: self.octaves, finest_level: self.finest_level });
+        }
+        if !(0. ..=1.).contains(&self.attenuation) {
+            return Err(ConfigError::Attenuation(self.attenuation));
+        }
+        if !(self.lacunarity > 1. && self.lacunarity.is_finite()) {
+            return Err(ConfigError::Lacunarity(self.lacunarity));
+        }
+        let (x, y) = self.feature_scale;
+        if !(x > 0. && y > 0. && x.is_finite() && y.is_finite()) {
+            return Err(ConfigError::FeatureScale(self.feature_scale));
+        }
+        if let Some(cap) = self.detail_cap {
+            if !cap.is_finite() || self.finest() >= self.octaves {
+                return Err(ConfigError::DetailCap(cap));
+            }
+        }
+        if self.coarsest() < self.finest() {
+            let (finest_level, coarsest_level) = (self.finest(), self.coarsest());
+            return Err(ConfigError::LevelRange { finest_level, coarsest_level });
+        }
+        if let Some((angle, aspect)) = self.anisotropy {
+            if !(angle.is_finite() && aspect > 0. && aspect.is_finite()) {
+                return Err(ConfigError::Anisotropy((angle, aspect)));
+            }
+            if aspect != 1. && (self.wrap_x || self.wrap_y) {
+                return Err(ConfigError::AnisotropicWrap);
+            }
+        }
+        if self.octave_rotation && (self.wrap_x || self.wrap_y) {
+            return Err(ConfigError::RotatedWrap);
+        }
+        let (lo, hi) = self.clamp_range;
+        if !(lo < hi && lo.is_finite() && hi.is_finite()) {
+            return Err(ConfigError::ClampRange(self.clamp_range));
+        }
+        if let Some((_, power, _)) = self.bump.filter(|&(_, power, _)| !(power >= 0. && power.is_finite())) {
+            return Err(ConfigError::BumpPower(power));
+        }
+        if let Some(mask) = self.region_mask.as_ref().filter(|m| m.dim() != (self.height, self.width)) {
+            let (height, width) = mask.dim();
+            return Err(ConfigError::RegionMask { width, height });
+        }
+        if let Some(curve) = self.curve.as_ref().filter(|c| !c.is_valid()) {
+            return Err(ConfigError::Curve(curve.clone()));
+        }
+        if let Some(level) = generate::oversized_level(self) {
+            return Err(ConfigError::GridSize { level });
+        }
+
+        let weights: Vec<f32> = self.levels().map(|(_, scale)| scale).collect();
+        if weights.iter().any(|w| !w.is_finite()) {
+            return Err(ConfigError::NotFinite("octave_weights"));
+        }
+        if self.normalization == Normalization::ScaleSum && weights.iter().sum::<f32>() == 0. {
+            return Err(ConfigError::ZeroWeights);
+        }
+        let params = [("perlin_weight", self.perlin_weight), ("warp_strength", self.warp_strength),
+            ("gain", self.gain), ("bias", self.bias), ("grain", self.grain),
+            ("edge_fade", self.edge_fade.map_or(0., |(_, target)| target)),
+            ("bump", self.bump.map_or(0., |(amplitude, ..)| amplitude)),
+            ("bump", self.bump.map_or(0., |(.., (x, _))| x)), ("bump", self.bump.map_or(0., |(.., (_, y))| y)),
+            ("region_fill", self.region_fill)];
+        match params.into_iter().find(|(_, v)| !v.is_finite()) {
+            Some((name, _)) => Err(ConfigError::NotFinite(name)),
+            None => Ok(()),
+        }
+    }
+
+    /// Panics with the error if [`validate`](Self::validate) fails.
+    pub(crate) fn expect_valid(&self) {
+        if let Err(e) = self.validate() {
+            panic!("invalid heightmap config: {e}");
+        }
+    }
+
+    /// The same terrain at a lower resolution, `size` pixels along the longer axis, for a quick
+    /// look before a large render.
+    ///
+    /// Gradient cells, warping, the origin and any region mask shrink with the map, so hashed
+    /// gradients land on the same lattice and the preview is the full map scaled down. Octaves
+    /// with cells smaller than a preview pixel are skipped, which changes
+    /// [`Normalization::ScaleSum`] very slightly. Erosion works on pixels, so it is only scaled
+    /// roughly: the talus grows with the coarser pixels, and the droplet count and length shrink
+    /// with the map.
+    pub fn preview(&self, size: usize) -> Self {
+        let s = size as f32 / self.width.max(self.height) as f32;
+        let scaled = |dim: usize| ((dim as f32 * s).round() as usize).max(1);
+        let mut preview = self.clone().with_size(scaled(self.width), scaled(self.height));
+
+        // cells of level `l` are `lacunarity^l * s` preview pixels
+        let subpixel = (-s.ln() / self.lacunarity.ln()).ceil().max(0.) as usize;
+        let top = self.octaves.saturating_sub(1).min(self.coarsest());
+        preview.finest_level = self.finest().max(subpixel).min(top);
+        preview.detail_cap = None;
+        preview.feature_scale = (self.feature_scale.0 * s, self.feature_scale.1 * s);
+        preview.warp_strength *= s;
+        preview.edge_fade = self.edge_fade.map(|(margin, target)| ((margin as f32 * s).round() as usize, target));
+        let shrink = |p: i64| (p as f64 * s as f64).round() as i64;
+        preview.origin = (shrink(self.origin.0), shrink(self.origin.1));
+        // nearest neighbour, from the pixel under each preview pixel's centre
+        preview.region_mask = self.region_mask.as_ref().map(|mask| {
+            let (height, width) = (preview.height, preview.width);
+            let under = |k: usize, n: usize, of: usize| {
+                (((k as f32 + 0.5) * of as f32 / n as f32) as usize).min(of.saturating_sub(1))
+            };
+            Array2::from_shape_fn([height, width], |(i, j)| {
+                mask.get([under(i, height, mask.nrows()), under(j, width, mask.ncols())]).copied().unwrap_or(false)
+            })
+        });
+
+        if let Some((_, talus)) = &mut preview.thermal_erosion {
+            *talus /= s;
+        }
+        if let Some((droplets, params)) = &mut preview.hydraulic_erosion {
+            *droplets = ((*droplets as f32 * s * s).round() as usize).max(1);
+            params.max_steps = ((params.max_steps as f32 * s).round() as usize).max(1);
+        }
+        preview
+    }
+
+    /// Levels of the generated octaves whose cells are larger than the map along both axes,
+    /// coarsest first. Such an octave covers less than one cell, so it adds little more than a
+    /// gentle slope; lowering `octaves` by their number loses next to nothing.
+    pub fn degenerate_octaves(&self) -> Vec<usize> {
+        self.levels()
+            .map(|(level, _)| level)
+            .filter(|&level| {
+                let cell = self.cell_size(level);
+                cell * self.feature_scale.0 > self.width as f32 && cell * self.feature_scale.1 > self.height as f32
+            })
+            .collect()
+    }
+
+    /// Generates a `height` × `width` heightmap, indexed `[row, column]`.
+    ///
+    /// Octaves run from the coarsest down to single-pixel cells; each octave's amplitude is
+    /// `1 - attenuation` times the previous one, unless `octave_weights` are given. Each octave
+    /// is shaped by `style`, and the sum is scaled according to `normalization`, then blended
+    /// with a squircle falloff that lowers the edges of the map.
+    ///
+    /// The result is roughly in `-1..1` towards the centre of the map, and dips below `-1` near
+    /// the corners; callers are expected to clamp before quantizing.
+    ///
+    /// Panics if [`validate`](Self::validate) fails.
+    pub fn generate(&self) -> Array2<f32> {
+        self.expect_valid();
+        generate::generate(self, Source::Seed, &|_| {})
+    }
+
+    /// Like [`generate`](Self::generate), calling `progress` with the fraction of the map done
+    /// so far, about once per percent of rows and with `1.` once any erosion has finished too.
+    ///
+    /// With the `parallel` feature, rows are filled in parallel, so `progress` may be called from
+    /// several threads and slightly out of order.
+    pub fn generate_with_progress(&self, progress: impl Fn(f32) + Sync) -> Array2<f32> {
+        self.expect_valid();
+        generate::generate(self, Source::Seed, &progress)
+    }
+
+    /// The map [`generate`](Self::generate) makes, as successive strips of `rows` rows each,
+    /// the last one possibly shorter, for maps too large to hold in memory at once. Only the
+    /// gradient grids and the current strip are kept.
+    ///
+    /// Panics if `rows` is 0, or if the map needs a pass over all of it: with `MinMax` or
+    /// `Equalize` normalization, which need the map's whole range, or with erosion. Use
+    /// [`Normalization::ScaleSum`] or [`Normalization::None`] instead.
+    pub fn generate_strips(&self, rows: usize) -> impl Iterator<Item = Array2<f32>> + '_ {
+        self.expect_valid();
+        generate::strips(self, rows)
+    }
+
+    /// The height [`generate`](Self::generate) gives the point `x` pixels across and `y` down
+    /// the map, worked out on its own, with only the gradients around the point hashed as they
+    /// are needed instead of grids filled for the whole map. At a pixel it agrees exactly with
+    /// the generated map, so `map[[i, j]]` is `height_at(j as f32, i as f32)`.
+    ///
+    /// Points off the map are fine: the noise carries on across the same world as maps with a
+    /// different [`origin`](Self::origin), and repeats along wrapping axes. The squircle blend,
+    /// bump and island falloff still follow the position on this map, so set `perlin_weight` to 1 to
+    /// sample an unbounded world; warping folds displaced points back onto the map. Points in
+    /// pixels outside the region mask are its fill, while points off the map aren't masked.
+    ///
+    /// ```
+    /// use heightmap_gen::HeightmapConfig;
+    ///
+    /// let config = HeightmapConfig::default().with_size(64, 48);
+    /// assert_eq!(config.height_at(10., 20.), config.generate()[[20, 10]]);
+    /// ```
+    ///
+    /// Panics if [`validate`](Self::validate) fails, or unless `gradients` is
+    /// [`GradientSource::Hashed`] with [`Normalization::ScaleSum`] or [`Normalization::None`],
+    /// no erosion and no grain, which all need the whole map.
+    pub fn height_at(&self, x: f32, y: f32) -> f32 {
+        self.expect_valid();
+        generate::height_at(self, y, x)
+    }
+
+    /// The map [`generate`](Self::generate) makes, along with the exact slope of the heights
+    /// along the rows and columns at every pixel, from the derivatives of each octave's noise
+    /// rather than by differences between pixels. They make sharper normal maps than
+    /// [`normal_map`](crate::normal_map), see
+    /// [`normal_map_from_gradient`](crate::normal_map_from_gradient).
+    ///
+    /// Panics unless `noise` is [`NoiseKind::Perlin`] with no warping and
+    /// [`Normalization::ScaleSum`] or [`Normalization::None`], and only the squircle blend, gain,
+    /// bias and inversion follow: no bump, island, edge fade, terraces, erosion, grain or region
+    /// mask.
+    pub fn generate_with_gradient(&self) -> (Array2<f32>, Array2<(f32, f32)>) {
+        self.expect_valid();
+        generate::generate_with_gradient(self)
+    }
+
+    /// Adds this configuration's octaves to `base`, such as a hand-sculpted map read with
+    /// [`read_png`](crate::read_png), so that `base` sets the low frequencies and the octaves
+    /// fill in fine detail. Set `octaves` to the number of fine levels wanted.
+    ///
+    /// The octaves are summed like in [`generate`](Self::generate) at the size of `base`, ignoring
+    /// `width` and `height`, and normalized according to `normalization`; they are then
+    /// multiplied by `strength` and added on with none of the later stages.
+    ///
+    /// Panics if [`validate`](Self::validate) fails.
+    pub fn generate_detail(&self, base: &Array2<f32>, strength: f32) -> Array2<f32> {
+        let config = self.clone().with_size(base.ncols(), base.nrows());
+        config.expect_valid();
+        generate::detail(&config, base, strength)
+    }
+
+    /// Generates a disk of terrain as wide as the shorter side of the map, centred on it, for
+    /// planet textures and other round maps. Pixels outside the disk are set to `fill`.
+    ///
+    /// The octaves are sampled by each pixel's angle around the centre and distance from it,
+    /// with gradients that wrap around the angle, so there is no seam where it comes back to
+    /// zero. Features are as large as on a rectangular map at the rim and are pinched together
+    /// towards the centre, where all angles meet. `wrap_x`, `wrap_y`, `anisotropy` and
+    /// `octave_rotation` are ignored; the later stages work as for [`generate`](Self::generate), on the whole map.
+    ///
+    /// Panics if [`validate`](Self::validate) fails.
+    pub fn generate_disk(&self, fill: f32) -> Array2<f32> {
+        self.expect_valid();
+        generate::disk(self, fill)
+    }
+
+    /// The gradient grids [`generate`](Self::generate) fills before summing the octaves, to save
+    /// with [`GradientGrids::write`] and generate the same map from later with
+    /// [`generate_from_grids`](Self::generate_from_grids).
+    ///
+    /// Panics if [`validate`](Self::validate) fails.
+    pub fn gradient_grids(&self) -> GradientGrids {
+        self.expect_valid();
+        generate::gradient_grids(self)
+    }
+
+    /// Like [`generate`](Self::generate), but with the octave and warp gradients taken from
+    /// `grids` instead of the seed, so the map comes out the same wherever the grids were made.
+    /// Erosion droplets and grain are still drawn from `seed`.
+    ///
+    /// ```
+    /// use heightmap_gen::{GradientGrids, HeightmapConfig};
+    ///
+    /// let config = HeightmapConfig::default().with_size(64, 48);
+    /// let mut file = Vec::new();
+    /// config.gradient_grids().write(&mut file).unwrap();
+    /// let grids = GradientGrids::read(&file[..]).unwrap();
+    /// assert_eq!(config.generate_from_grids(&grids).unwrap(), config.generate());
+    /// ```
+    ///
+    /// Returns an error if the number or size of the grids doesn't match the ones this
+    /// configuration lays out, and panics if [`validate`](Self::validate) fails.
+    pub fn generate_from_grids(&self, grids: &GradientGrids) -> Result<Array2<f32>, GradientsError> {
+        self.expect_valid();
+        generate::from_grids(self, grids)
+    }
+
+    /// Each octave's contribution to [`generate`](Self::generate) as a map of its own, coarsest
+    /// first: the noise after `style` shaping and warping, multiplied by the octave's amplitude
+    /// if `scaled` is set. The layers aren't normalized or blended with anything.
+    pub fn generate_octaves(&self, scaled: bool) -> Vec<Array2<f32>> {
+        self.expect_valid();
+        generate::octave_layers(self, scaled)
+    }
+
+    /// How far domain warping moves each pixel before the octaves are sampled, as `(x, y)`
+    /// pixels along the columns and down the rows, for inspecting the warp; all zeros if
+    /// `warp_strength` is 0. [`offset_colors`](crate::offset_colors) renders it.
+    pub fn warp_offsets(&self) -> Array2<(f32, f32)> {
+        self.expect_valid();
+        generate::warp_offsets(self)
+    }
+
+    /// Like [`generate`](Self::generate), but draws all randomness from `rng` instead of
+    /// `seed`: the octave gradients, coarsest first, unless
+    /// [`octave_seeds`](Self::octave_seeds) is set, then the warp fields, erosion droplets and
+    /// grain when those are enabled.
+    ///
+    /// [`GradientSource::Hashed`] gradients, the default, don't draw from an RNG and still come
+    /// from `seed`; use [`GradientSource::RandomAngles`] to have `rng` pick them too.
+    ///
+    /// ```
+    /// use heightmap_gen::HeightmapConfig;
+    /// use rand::{rngs::StdRng, SeedableRng};
+    ///
+    /// let map = HeightmapConfig::default().with_size(64, 64).generate_with_rng(&mut StdRng::seed_from_u64(7));
+    /// ```
+    pub fn generate_with_rng(&self, rng: &mut impl RngCore) -> Array2<f32> {
+        self.expect_valid();
+        generate::generate(self, Source::Rng(rng), &|_| {})
+    }
+}
+
+/// Reads [`HeightmapConfig::seed`], each word an integer or a string of decimal or hex digits.
+#[cfg(feature = "config-file")]
+fn deserialize_seed<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<(u64, u64), D::Error> {
+    #[derive(serde::Deserialize)]
+    #[serde(untagged)]
+    enum Word {
+        Int(u64),
+        Text(String),
+    }
+
+    let parse = |word| match word {
+        Word::Int(v) => Ok(v),
+        Word::Text(s) => match s.strip_prefix("0x") {
+            Some(hex) => u64::from_str_radix(hex, 16),
+            None => s.parse(),
+        }.map_err(|e| serde::de::Error::custom(format!("invalid seed word {s:?}: {e}"))),
+    };
+    let (a, b) = serde::Deserialize::deserialize(deserializer)?;
+    Ok((parse(a)?, parse(b)?))
+}
+
+/// The attenuation that makes each octave `lacunarity^(-h)` times as strong as the one before.
+pub fn attenuation_from_hurst(h: f32, lacunarity: f32) -> f32 {
+    1. - lacunarity.powf(-h)
+}
+
+/// The Hurst exponent of an octave falloff given as an attenuation; the inverse of
+/// [`attenuation_from_hurst`].
+pub fn hurst_from_attenuation(attenuation: f32, lacunarity: f32) -> f32 {
+    -(1. - attenuation).ln() / lacunarity.ln()
+}
+
+/// Why [`HeightmapConfig::validate`] rejected a configuration.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ConfigError {
+    /// The map has no pixels.
+    EmptyMap { width: usize, height: usize },
+    /// No octave level is generated, because `octaves` is 0 or `finest_level` isn't below it.
+    NoOctaves { octaves: usize, finest_level: usize },
+    /// `detail_cap` isn't finite, or no octave has cells as large as it.
+    DetailCap(f32),
+    /// `coarsest_level` is below `finest_level`, or the level `detail_cap` raises it to, leaving
+    /// no level between them.
+    LevelRange { finest_level: usize, coarsest_level: usize },
+    /// `attenuation` is outside `0..=1`.
+    Attenuation(f32),
+    /// `lacunarity` isn't finite and greater than 1.
+    Lacunarity(f32),
+    /// `feature_scale` isn't positive and finite along both axes.
+    FeatureScale((f32, f32)),
+    /// The `anisotropy` angle isn't finite or its aspect isn't positive and finite.
+    Anisotropy((f32, f32)),
+    /// The map wraps around but is stretched with an `anisotropy` aspect other than 1, which
+    /// grids of whole cells can't tile.
+    AnisotropicWrap,
+    /// The map wraps around but has `octave_rotation` set, and grids turned at an angle can't
+    /// tile.
+    RotatedWrap,
+    /// `clamp_range` is empty or not finite.
+    ClampRange((f32, f32)),
+    /// The `region_mask` is `width` × `height` pixels, not the size of the map.
+    RegionMask { width: usize, height: usize },
+    /// The `bump` power is negative or not finite.
+    BumpPower(f32),
+    /// The cells of octave `level` are so small that its gradient grid would have more than 16
+    /// points per pixel of the map, each under a quarter of a pixel across, or more than fit in
+    /// memory. A larger `feature_scale` makes them larger.
+    GridSize { level: usize },
+    /// The octave amplitudes add up to 0, which [`Normalization::ScaleSum`] divides by.
+    ZeroWeights,
+    /// The `curve` has a gamma that isn't positive and finite, or control points that are
+    /// missing, not finite or out of order.
+    Curve(Curve),
+    /// The named setting is NaN or infinite.
+    NotFinite(&'static str),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::EmptyMap { width, height } => write!(f, "a {width} × {height} map has no pixels"),
+            ConfigError::NoOctaves { octaves, finest_level } => {
+                write!(f, "no octaves to generate with {octaves} octaves from finest level {finest_level}")
+            }
+            ConfigError::DetailCap(cap) => write!(f, "no octave has cells of at least {cap} pixels"),
+            ConfigError::LevelRange { finest_level, coarsest_level } => {
+                write!(f, "coarsest level {coarsest_level} is below finest level {finest_level}")
+            }
+            ConfigError::Attenuation(a) => write!(f, "attenuation must be between 0 and 1, got {a}"),
+            ConfigError::Lacunarity(l) => write!(f, "lacunarity must be greater than 1, got {l}"),
+            ConfigError::FeatureScale((x, y)) => write!(f, "feature scale must be positive, got {x} {y}"),
+            ConfigError::Anisotropy((angle, aspect)) => {
+                write!(f, "anisotropy needs a finite angle and positive aspect, got {angle} {aspect}")
+            }
+            ConfigError::AnisotropicWrap => write!(f, "wrapping maps can't be stretched anisotropically"),
+            ConfigError::RotatedWrap => write!(f, "wrapping maps can't have rotated octaves"),
+            ConfigError::ClampRange((lo, hi)) => write!(f, "clamp range {lo}..{hi} is empty"),
+            ConfigError::RegionMask { width, height } => {
+                write!(f, "the region mask is {width} × {height}, not the size of the map")
+            }
+            ConfigError::BumpPower(power) => write!(f, "bump power must be at least 0, got {power}"),
+            ConfigError::GridSize { level } => {
+                write!(f, "the cells of octave level {level} are too small for a gradient grid over the map")
+            }
+            ConfigError::ZeroWeights => write!(f, "octave weights add up to 0, so scale-sum can't normalize them"),
+            ConfigError::Curve(curve) => write!(f, "{curve:?} isn't a usable response curve"),
+            ConfigError::NotFinite(name) => write!(f, "{name} must be finite"),
+        }
+    }
+}
+
+impl Error for ConfigError {}
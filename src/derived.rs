@@ -0,0 +1,227 @@
+use ndarray::{Array2, Zip};
+
+use crate::{blur, par::map_indexed};
+
+/// Height gradient along rows and columns at a pixel, from central differences. At the edges of
+/// the map they are one-sided, unless the map wraps around along that axis, as `(x, y)` in
+/// `wrap`; then the pixel on the opposite edge is the neighbour.
+pub(crate) fn gradient(map: &Array2<f32>, i: usize, j: usize, (wrap_x, wrap_y): (bool, bool)) -> (f32, f32) {
+    fn diff(line: impl Fn(usize) -> f32, p: usize, len: usize, wraps: bool) -> f32 {
+        if wraps { return (line((p + 1) % len) - line((p + len - 1) % len)) / 2.; }
+        let (lo, hi) = (p.saturating_sub(1), (p + 1).min(len - 1));
+        if hi == lo { 0. } else { (line(hi) - line(lo)) / (hi - lo) as f32 }
+    }
+
+    let (height, width) = map.dim();
+    let gi = diff(|k| map[[k, j]], i, height, wrap_y);
+    let gj = diff(|k| map[[i, k]], j, width, wrap_x);
+    (gi, gj)
+}
+
+/// Tangent-space normal map of a heightmap, as RGB with green pointing up the image (the
+/// OpenGL convention).
+///
+/// `strength` scales the height gradients before the normals are taken; heights in `-1..1` on a
+/// map hundreds of pixels wide need a strength in the tens to show much relief.
+///
+/// `wrap` says whether the map wraps around along `(x, y)`, like maps generated with
+/// [`wrap_x`](crate::HeightmapConfig::wrap_x) and `wrap_y`; the normals then tile too.
+pub fn normal_map(map: &Array2<f32>, strength: f32, wrap: (bool, bool)) -> Array2<[u8; 3]> {
+    Array2::from_shape_fn(map.dim(), |(i, j)| encode_normal(gradient(map, i, j, wrap), strength))
+}
+
+/// Like [`normal_map`], from height gradients along the rows and columns worked out some other
+/// way, such as the exact ones of
+/// [`HeightmapConfig::generate_with_gradient`](crate::HeightmapConfig::generate_with_gradient).
+pub fn normal_map_from_gradient(gradient: &Array2<(f32, f32)>, strength: f32) -> Array2<[u8; 3]> {
+    gradient.map(|&g| encode_normal(g, strength))
+}
+
+fn encode_normal((gi, gj): (f32, f32), strength: f32) -> [u8; 3] {
+    let encode = |v: f32| ((v * 0.5 + 0.5) * 255.).round() as u8;
+
+    // x runs along the columns and y up the rows
+    let (nx, ny, nz) = (-gj * strength, gi * strength, 1.);
+    let len = (nx * nx + ny * ny + nz * nz).sqrt();
+    [encode(nx / len), encode(ny / len), encode(nz / len)]
+}
+
+/// Directions along the contour lines of a heightmap, the curl of the height field: at each
+/// pixel, a unit `(x, y)` vector perpendicular to the gradient, with x along the columns and y
+/// down the rows, turned so that higher ground is on the right. Flat pixels get `(0, 0)`.
+/// `wrap` works as for [`normal_map`].
+pub fn flow_field(map: &Array2<f32>, wrap: (bool, bool)) -> Array2<(f32, f32)> {
+    Array2::from_shape_fn(map.dim(), |(i, j)| {
+        let (gi, gj) = gradient(map, i, j, wrap);
+        let len = gi.hypot(gj);
+        if len == 0. { (0., 0.) } else { (gi / len, -gj / len) }
+    })
+}
+
+/// Colours a [`flow_field`] by direction, with the angle of each vector as the hue: red points
+/// along +x, and hues turn through yellow and green towards +y. Zero vectors are black.
+pub fn flow_colors(field: &Array2<(f32, f32)>) -> Array2<[u8; 3]> {
+    field.map(|&(x, y)| {
+        if x == 0. && y == 0. { return [0; 3]; }
+        // hue in sixths of a turn, then the fully saturated colour on that part of the wheel
+        let h = y.atan2(x).rem_euclid(std::f32::consts::TAU) / std::f32::consts::TAU * 6.;
+        let f = |n: f32| {
+            let k = (n + h) % 6.;
+            ((1. - (k.min(4. - k)).clamp(0., 1.)) * 255.).round() as u8
+        };
+        [f(5.), f(3.), f(1.)]
+    })
+}
+
+/// Colours a field of `(x, y)` offsets such as
+/// [`HeightmapConfig::warp_offsets`](crate::HeightmapConfig::warp_offsets), with x in the red
+/// channel and y in the green: no offset is `(128, 128, 0)`, and offsets of `max` or more
+/// either way reach 0 or 255.
+pub fn offset_colors(field: &Array2<(f32, f32)>, max: f32) -> Array2<[u8; 3]> {
+    let scale = if max > 0. { 0.5 / max } else { 0. };
+    let encode = |d: f32| ((d * scale + 0.5).clamp(0., 1.) * 255.).round() as u8;
+    field.map(|&(x, y)| [encode(x), encode(y), 0])
+}
+
+/// Steepness of a heightmap: the gradient magnitude at each pixel, divided by the largest one so
+/// the steepest slope on the map is 1 and flat ground is 0. `wrap` works as for [`normal_map`].
+pub fn slope_map(map: &Array2<f32>, wrap: (bool, bool)) -> Array2<f32> {
+    let mut res = Array2::from_shape_fn(map.dim(), |(i, j)| {
+        let (gi, gj) = gradient(map, i, j, wrap);
+        gi.hypot(gj)
+    });
+
+    let max = res.fold(0f32, |m, &v| m.max(v));
+    if max > 0. {
+        res /= max;
+    }
+    res
+}
+
+/// A cheap ambient occlusion estimate: how far each pixel lies below a Gaussian [`blur`] of the
+/// map with standard deviation `radius` pixels, flipped and divided by the largest such depth,
+/// so the most sunken pixel on the map is 0 and pixels level with or above their surroundings,
+/// ridges and flat ground, are 1.
+pub fn blur_occlusion(map: &Array2<f32>, radius: f32) -> Array2<f32> {
+    let mut surroundings = map.clone();
+    blur(&mut surroundings, radius);
+    let mut res = Zip::from(&surroundings).and(map).map_collect(|&s, &v| (s - v).max(0.));
+
+    let max = res.fold(0f32, |m, &v| m.max(v));
+    if max > 0. {
+        res /= max;
+    }
+    res.mapv_inplace(|d| 1. - d);
+    res
+}
+
+/// Ambient occlusion of a heightmap in `0..1`, from the horizons around each pixel: in each of
+/// `samples` evenly spread directions, heights are sampled a pixel apart out to `radius` pixels,
+/// and the highest elevation angle over the pixel is the horizon that way. Each pixel is lit by
+/// the sky above its horizons, the average of one minus their sines, so flat ground and peaks
+/// are 1, and valley floors and pits darker.
+///
+/// Angles are measured with `radius` pixels counting as one unit of height, so a rise of 1 at
+/// the edge of the radius is a horizon 45° up; a wider radius finds larger valleys but also
+/// flattens them. Pixels past the edges of the map take the height of the nearest edge pixel,
+/// unless the map wraps around along that axis; then the horizons carry on from the opposite
+/// edge, so the occlusion tiles too. `wrap` works as for [`normal_map`].
+///
+/// Costs `samples × radius` bilinear samples per pixel, rounding the radius up: 8 directions out
+/// to 16 pixels, as the `occlusion` output of the command line, make 128 and take several times
+/// as long as generating the map did. Fewer of either give a rougher estimate sooner.
+///
+/// Panics if `samples` is 0.
+pub fn ambient_occlusion(map: &Array2<f32>, samples: usize, radius: f32, wrap: (bool, bool)) -> Array2<f32> {
+    assert!(samples > 0, "ambient occlusion needs at least one direction");
+    let steps = radius.ceil().max(1.) as usize;
+    let directions: Vec<(f32, f32)> = (0..samples)
+        .map(|k| (k as f32 / samples as f32 * std::f32::consts::TAU).sin_cos())
+        .collect();
+
+    map_indexed(map, |(i, j), v| {
+        let sky: f32 = directions.iter().map(|&(dy, dx)| {
+            // the steepest rise, as height over distance in units of the radius
+            let slope = (1..=steps).fold(0f32, |max, k| {
+                let d = k as f32 * radius / steps as f32;
+                let rise = sample_wrapped(map, j as f32 + dx * d, i as f32 + dy * d, wrap) - v;
+                max.max(rise / (d / radius))
+            });
+            1. - slope / slope.hypot(1.)
+        }).sum();
+        sky / samples as f32
+    })
+}
+
+/// Bilinear sample at `(fx, fy)` like [`sample_bilinear`](crate::sample_bilinear), but wrapping
+/// the coordinates around the axes that `wrap` says the map tiles along instead of clamping them.
+fn sample_wrapped(map: &Array2<f32>, fx: f32, fy: f32, (wrap_x, wrap_y): (bool, bool)) -> f32 {
+    // the two pixels either side of a coordinate along one axis, and how far it is past the first
+    fn span(f: f32, len: usize, wraps: bool) -> (usize, usize, f32) {
+        let f = if wraps { f.rem_euclid(len as f32) } else { f.clamp(0., (len - 1) as f32) };
+        let p = (f as usize).min(len - 1);
+        let next = if wraps { (p + 1) % len } else { (p + 1).min(len - 1) };
+        (p, next, f - p as f32)
+    }
+
+    let (height, width) = map.dim();
+    let (x0, x1, tx) = span(fx, width, wrap_x);
+    let (y0, y1, ty) = span(fy, height, wrap_y);
+    let top = map[[y0, x0]] + (map[[y0, x1]] - map[[y0, x0]]) * tx;
+    let bottom = map[[y1, x0]] + (map[[y1, x1]] - map[[y1, x0]]) * tx;
+    top + (bottom - top) * ty
+}
+
+/// Height, steepness and occlusion of a heightmap packed into the channels of one RGB image, for
+/// shaders that would otherwise sample three textures: red is the height clamped to `range` and
+/// mapped onto the full `0..=255`, green the [`slope_map`] and blue the [`blur_occlusion`] over
+/// `radius` pixels. `wrap` works as for [`normal_map`].
+pub fn packed_map(map: &Array2<f32>, range: (f32, f32), radius: f32, wrap: (bool, bool)) -> Array2<[u8; 3]> {
+    let (lo, hi) = range;
+    let (slopes, occlusion) = (slope_map(map, wrap), blur_occlusion(map, radius));
+    let byte = |v: f32| (v.clamp(0., 1.) * 255.).round() as u8;
+    Zip::from(map).and(&slopes).and(&occlusion)
+        .map_collect(|&v, &s, &o| [byte((v - lo) / (hi - lo)), byte(s), byte(o)])
+}
+
+/// Where a heightmap is above water: `true` for land, including pixels exactly at `sea_level`,
+/// and `false` for pixels below it.
+pub fn land_mask(map: &Array2<f32>, sea_level: f32) -> Array2<bool> {
+    map.mapv(|v| v >= sea_level)
+}
+
+/// Height bands of deep water, shallows, beach, grass, forest, rock and snow, classes 0 to 6
+/// in that order, with the shoreline at height 0 as for
+/// [`ColorRamp::terrain`](crate::ColorRamp::terrain); see [`classify`] and
+/// [`TERRAIN_PALETTE`](crate::TERRAIN_PALETTE).
+pub const TERRAIN_BANDS: [(f32, u8); 7] =
+    [(-1., 0), (-0.25, 1), (0., 2), (0.05, 3), (0.2, 4), (0.55, 5), (0.8, 6)];
+
+/// Sorts every pixel of a heightmap into a class by height: `bands` are `(lowest height, class)`
+/// pairs in any order, and each pixel takes the class of the highest band starting at or below
+/// it. Pixels below every band take the class of the lowest, so it covers the deepest water
+/// whatever its height. Write the classes with [`write_indexed_png`](crate::write_indexed_png),
+/// or take the ids themselves with [`Array2::into_raw_vec`].
+///
+/// Panics if `bands` is empty.
+pub fn classify(map: &Array2<f32>, bands: &[(f32, u8)]) -> Array2<u8> {
+    assert!(!bands.is_empty(), "classifying needs at least one band");
+    let mut bands = bands.to_vec();
+    bands.sort_by(|a, b| a.0.total_cmp(&b.0));
+    map.mapv(|v| bands[bands.partition_point(|&(lo, _)| lo <= v).max(1) - 1].1)
+}
+
+/// The change from heightmap `a` to `b` at each pixel as an image in `0..1` for
+/// [`write_gray_png`](crate::write_gray_png): signed, with no change at mid-gray and higher
+/// ground in `b` brighter, or as the absolute difference, with no change black. Differences are
+/// multiplied by `gain` first, so small ones show; heights a full `-1..1` range apart reach the
+/// ends at a gain of 1.
+///
+/// Panics if the maps differ in size.
+pub fn difference_image(a: &Array2<f32>, b: &Array2<f32>, absolute: bool, gain: f32) -> Array2<f32> {
+    assert_eq!(a.dim(), b.dim(), "the maps must be the same size");
+    Zip::from(a).and(b).map_collect(|&a, &b| {
+        let d = (b - a) * gain / 2.;
+        if absolute { d.abs() } else { d / 2. + 0.5 }
+    })
+}
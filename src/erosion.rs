@@ -0,0 +1,196 @@
+use ndarray::Array2;
+use rand::Rng;
+
+/// Offsets of the four edge-adjacent neighbours of a cell.
+const NEIGHBOURS: [(isize, isize); 4] = [(-1, 0), (1, 0), (0, -1), (0, 1)];
+
+/// The index `d` steps from `p` along an axis of length `len`, coming back in from the other
+/// end if the axis wraps around, and `None` past the end otherwise.
+fn offset(p: usize, d: isize, len: usize, wraps: bool) -> Option<usize> {
+    if wraps { return Some((p as isize + d).rem_euclid(len as isize) as usize); }
+    p.checked_add_signed(d).filter(|&q| q < len)
+}
+
+/// Indices of the four edge-adjacent neighbours of `(i, j)` inside `field`; those past an edge
+/// come around from the opposite one if the map wraps along `(x, y)` in `wrap`, and are left
+/// out otherwise. On maps only one or two pixels across, a neighbour can repeat or be `(i, j)`.
+pub(crate) fn neighbours4<T>(field: &Array2<T>, i: usize, j: usize, (wrap_x, wrap_y): (bool, bool))
+    -> impl Iterator<Item = (usize, usize)> {
+    let (height, width) = field.dim();
+    NEIGHBOURS.into_iter()
+        .filter_map(move |(di, dj)| Some((offset(i, di, height, wrap_y)?, offset(j, dj, width, wrap_x)?)))
+}
+
+/// Thermal erosion: material slides off any cell that is more than `talus` higher than one of
+/// its four neighbours, until no such slope is left or `iterations` passes have run.
+///
+/// `talus` is a height difference between adjacent pixels, in the same units as the map. Each
+/// pass moves half of the excess over the steepest slope, split between the lower neighbours in
+/// proportion to how far they are below the talus; the total amount of material is preserved.
+///
+/// `wrap` says whether the map wraps around along `(x, y)`; material then slides across the
+/// edges onto the opposite ones, so the eroded map still tiles.
+pub fn thermal_erode(field: &mut Array2<f32>, iterations: usize, talus: f32, wrap: (bool, bool)) {
+    let (height, width) = field.dim();
+    let mut delta = Array2::from_elem(field.dim(), 0f32);
+
+    for _ in 0..iterations {
+        let mut moved = false;
+        delta.fill(0.);
+
+        for i in 0..height { for j in 0..width {
+            let h = field[[i, j]];
+            let lower = |(ni, nj): (usize, usize)| {
+                let d = h - field[[ni, nj]];
+                (d > talus).then_some((ni, nj, d))
+            };
+
+            let (max, total) = neighbours4(field, i, j, wrap).filter_map(lower)
+                .fold((0f32, 0f32), |(max, total), (_, _, d)| (max.max(d), total + d - talus));
+            if total <= 0. { continue; }
+
+            let amount = (max - talus) / 2.;
+            delta[[i, j]] -= amount;
+            for (ni, nj, d) in neighbours4(field, i, j, wrap).filter_map(lower) {
+                delta[[ni, nj]] += amount * (d - talus) / total;
+            }
+            moved = true;
+        }}
+
+        if !moved { break; }
+        *field += &delta;
+    }
+}
+
+/// Parameters of droplet-based [`hydraulic_erode`].
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "config-file", derive(serde::Deserialize), serde(default, deny_unknown_fields))]
+pub struct HydraulicParams {
+    /// How much of its previous direction a droplet keeps each step, in `0..1`; higher values
+    /// carve smoother, longer valleys.
+    pub inertia: f32,
+    /// Sediment a droplet can carry per unit of height drop, speed and water.
+    pub capacity: f32,
+    /// Floor on the carrying capacity, so droplets on nearly flat ground still erode a little.
+    pub min_capacity: f32,
+    /// Fraction of the spare capacity picked up as sediment each step.
+    pub erosion: f32,
+    /// Fraction of the excess sediment dropped each step.
+    pub deposition: f32,
+    /// Fraction of a droplet's water lost each step.
+    pub evaporation: f32,
+    /// Acceleration from height drops.
+    pub gravity: f32,
+    /// Steps after which a droplet is discarded.
+    pub max_steps: usize,
+}
+
+impl Default for HydraulicParams {
+    fn default() -> Self {
+        Self {
+            inertia: 0.05,
+            capacity: 4.,
+            min_capacity: 0.01,
+            erosion: 0.3,
+            deposition: 0.3,
+            evaporation: 0.01,
+            gravity: 4.,
+            max_steps: 30,
+        }
+    }
+}
+
+/// The rows and columns of the four pixels around a point inside the map, and how far the point
+/// is past the first of each. Along a wrapping axis the last pixel is followed by the first.
+fn cell(field: &Array2<f32>, x: f32, y: f32, (wrap_x, wrap_y): (bool, bool))
+    -> ([usize; 2], [usize; 2], f32, f32) {
+    let (height, width) = field.dim();
+    let (i, j) = (x as usize, y as usize);
+    let next = |p: usize, len: usize, wraps: bool| offset(p, 1, len, wraps).unwrap_or(p);
+    ([i, next(i, height, wrap_y)], [j, next(j, width, wrap_x)], x - i as f32, y - j as f32)
+}
+
+/// Bilinearly interpolated height and gradient at a point inside the map.
+fn height_and_gradient(field: &Array2<f32>, x: f32, y: f32, wrap: (bool, bool)) -> (f32, f32, f32) {
+    let ([i0, i1], [j0, j1], u, v) = cell(field, x, y, wrap);
+
+    let h00 = field[[i0, j0]];
+    let h01 = field[[i0, j1]];
+    let h10 = field[[i1, j0]];
+    let h11 = field[[i1, j1]];
+
+    let gx = (h10 - h00) * (1. - v) + (h11 - h01) * v;
+    let gy = (h01 - h00) * (1. - u) + (h11 - h10) * u;
+    let h = h00 * (1. - u) * (1. - v) + h10 * u * (1. - v) + h01 * (1. - u) * v + h11 * u * v;
+    (h, gx, gy)
+}
+
+/// Adds `amount` to the four pixels around a point, weighted by closeness.
+fn deposit(field: &mut Array2<f32>, x: f32, y: f32, amount: f32, wrap: (bool, bool)) {
+    let ([i0, i1], [j0, j1], u, v) = cell(field, x, y, wrap);
+
+    field[[i0, j0]] += amount * (1. - u) * (1. - v);
+    field[[i1, j0]] += amount * u * (1. - v);
+    field[[i0, j1]] += amount * (1. - u) * v;
+    field[[i1, j1]] += amount * u * v;
+}
+
+/// Hydraulic erosion: simulates `droplets` water droplets, each starting at a random point drawn
+/// from `rng`, that flow downhill picking up sediment where they speed up and dropping it where
+/// they slow down or pool.
+///
+/// `wrap` says whether the map wraps around along `(x, y)`; droplets then flow across the edges
+/// onto the opposite ones instead of being discarded there.
+pub fn hydraulic_erode(field: &mut Array2<f32>, droplets: usize, params: &HydraulicParams, wrap: (bool, bool),
+    rng: &mut impl Rng) {
+    let (height, width) = field.dim();
+    if height < 2 || width < 2 { return; }
+    // droplets must keep a whole cell of neighbours for the bilinear lookups, unless the cells
+    // past the last pixel come around to the first
+    let extent = |len: usize, wraps: bool| if wraps { len as f32 } else { (len - 1) as f32 };
+    let (max_x, max_y) = (extent(height, wrap.1), extent(width, wrap.0));
+
+    for _ in 0..droplets {
+        let mut x = rng.gen::<f32>() * max_x;
+        let mut y = rng.gen::<f32>() * max_y;
+        let (mut dx, mut dy) = (0f32, 0f32);
+        let mut speed = 1f32;
+        let mut water = 1f32;
+        let mut sediment = 0f32;
+
+        for _ in 0..params.max_steps {
+            let (h, gx, gy) = height_and_gradient(field, x, y, wrap);
+
+            dx = dx * params.inertia - gx * (1. - params.inertia);
+            dy = dy * params.inertia - gy * (1. - params.inertia);
+            let len = dx.hypot(dy);
+            if len == 0. { break; }
+            dx /= len;
+            dy /= len;
+
+            let (mut nx, mut ny) = (x + dx, y + dy);
+            if wrap.1 { nx = nx.rem_euclid(max_x); }
+            if wrap.0 { ny = ny.rem_euclid(max_y); }
+            if !(0. ..max_x).contains(&nx) || !(0. ..max_y).contains(&ny) { break; }
+
+            let dh = height_and_gradient(field, nx, ny, wrap).0 - h;
+            let capacity = (-dh * speed * water * params.capacity).max(params.min_capacity);
+
+            if sediment > capacity || dh > 0. {
+                // uphill, fill the pit behind the droplet at most up to the new height
+                let amount = if dh > 0. { dh.min(sediment) } else { (sediment - capacity) * params.deposition };
+                sediment -= amount;
+                deposit(field, x, y, amount, wrap);
+            } else {
+                // never dig deeper than the drop, which would leave a pit
+                let amount = ((capacity - sediment) * params.erosion).min(-dh);
+                sediment += amount;
+                deposit(field, x, y, -amount, wrap);
+            }
+
+            speed = (speed * speed - dh * params.gravity).max(0.).sqrt();
+            water *= 1. - params.evaporation;
+            (x, y) = (nx, ny);
+        }
+    }
+}
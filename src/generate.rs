@@ -0,0 +1,871 @@
+use std::{f32::consts::{PI, TAU}, iter, sync::atomic::{AtomicUsize, Ordering}};
+
+use ndarray::{Array, Array1, Array2, Dimension, Zip};
+use pcg_mwc::Mwc256XXA64;
+use rand::{Rng, RngCore};
+
+use crate::{
+    erosion::{hydraulic_erode, thermal_erode},
+    gradients::{GradientGrids, GradientsError},
+    kernel::{interpolate_linear, lattice_hash, perlin_with_deriv, table_gradient, HashedLattice, Lattice, Point},
+    noise::permutation,
+    par::{for_each, for_each_indexed, for_each_outer, map_indexed},
+    post::{equalize, invert, stretch, terrace},
+    GradientSource, HeightmapConfig, Invert, NoiseKind, Normalization,
+};
+
+/// Precision octaves are summed in; the `f64` feature trades some speed for less rounding drift
+/// when many octaves are stacked.
+#[cfg(not(feature = "f64"))]
+type Accum = f32;
+#[cfg(feature = "f64")]
+type Accum = f64;
+
+/// The gradient grid and sampling parameters of one octave.
+struct Octave<L = Array2<Point>> {
+    vecs: L,
+    cell_h: f32,
+    cell_w: f32,
+    /// Lattice position of the map's first pixel relative to the first grid point.
+    shift: (f32, f32),
+    scale: f32,
+    /// The stretch and rotation of pixel positions, if any, and the lowest transformed position
+    /// on the map, which the grid starts from.
+    transform: Option<(Transform, (f64, f64))>,
+}
+
+/// The linear map from pixel positions to noise coordinates that stretches features by `aspect`
+/// along a direction, for [`HeightmapConfig::anisotropy`].
+#[derive(Clone, Copy)]
+struct Stretch {
+    /// The direction as a unit vector along the rows and columns.
+    dir: (f64, f64),
+    /// How much of the component along `dir` to add on: `1 / aspect - 1`.
+    k: f64,
+}
+
+impl Stretch {
+    fn of(config: &HeightmapConfig) -> Option<Self> {
+        config.anisotropy.map(|(angle, aspect)| {
+            // the angle turns from the columns towards the rows, so clockwise on screen
+            let (s, c) = (angle as f64).sin_cos();
+            Stretch { dir: (s, c), k: 1. / aspect as f64 - 1. }
+        })
+    }
+
+    /// Point `(i, j)` with its component along the direction divided by the aspect ratio. At an
+    /// aspect ratio of 1, `k` is 0 and the point is returned exactly.
+    fn apply(self, i: f64, j: f64) -> (f64, f64) {
+        let d = (i * self.dir.0 + j * self.dir.1) * self.k;
+        (i + d * self.dir.0, j + d * self.dir.1)
+    }
+}
+
+/// The linear map from pixel positions to the noise coordinates of one octave: the
+/// [`Stretch`], if any, then the octave's rotation, if any.
+#[derive(Clone, Copy)]
+struct Transform {
+    stretch: Option<Stretch>,
+    /// The rotation as a row-major matrix, conjugated by the feature scale so that features
+    /// keep the proportions `feature_scale` gives them on screen whichever way they turn.
+    rotation: Option<[f64; 4]>,
+}
+
+impl Transform {
+    fn of(config: &HeightmapConfig, rotation: Option<f64>) -> Option<Self> {
+        let stretch = Stretch::of(config);
+        let (fw, fh) = (config.feature_scale.0 as f64, config.feature_scale.1 as f64);
+        let rotation = rotation.map(|angle| {
+            let (s, c) = angle.sin_cos();
+            [c, -s * fh / fw, s * fw / fh, c]
+        });
+        (stretch.is_some() || rotation.is_some()).then_some(Transform { stretch, rotation })
+    }
+
+    fn apply(self, i: f64, j: f64) -> (f64, f64) {
+        let (i, j) = self.stretch.map_or((i, j), |stretch| stretch.apply(i, j));
+        match self.rotation {
+            Some([a, b, c, d]) => (a * i + b * j, c * i + d * j),
+            None => (i, j),
+        }
+    }
+
+    /// The transposed map, which takes gradients with respect to the noise coordinates back to
+    /// pixel positions; the stretch is symmetric.
+    fn apply_transposed(self, i: f64, j: f64) -> (f64, f64) {
+        let (i, j) = match self.rotation {
+            Some([a, b, c, d]) => (a * i + c * j, b * i + d * j),
+            None => (i, j),
+        };
+        self.stretch.map_or((i, j), |stretch| stretch.apply(i, j))
+    }
+}
+
+/// Most lattice points an octave's gradient grid may have for each pixel of the map, counting
+/// a pixel's border around it; cells under a quarter of a pixel across only add aliasing.
+const MAX_POINTS_PER_PIXEL: usize = 16;
+
+/// Panic message of octaves whose gradient grid [`Octave::build`] won't lay out.
+const GRID_TOO_LARGE: &str = "octave cells too small for a gradient grid over the map";
+
+/// Key of the hashes per-octave sub-seeds are drawn from.
+const OCTAVE_SEED_KEY: u64 = 0x3F84D5B5B5470917;
+
+/// Key of the hashes octave rotations are drawn from.
+const ROTATION_KEY: u64 = 0xC0AC29B7C97C50DD;
+
+/// The angle octave `level` is rotated by, if [`HeightmapConfig::octave_rotation`] is set: a
+/// hash of the seed and the level, so skipping levels leaves the others' angles alone.
+fn rotation(config: &HeightmapConfig, level: usize) -> Option<f64> {
+    config.octave_rotation.then(|| {
+        let hash = lattice_hash(config.seed, ROTATION_KEY, level as i64, 0);
+        (hash >> 11) as f64 / (1u64 << 53) as f64 * TAU as f64
+    })
+}
+
+/// Tag of the RNG stream the domain warp fields are drawn from.
+const WARP_STREAM: u64 = 0xA4093822299F31D0;
+/// Tag of the RNG stream hydraulic erosion droplets start from.
+pub(crate) const HYDRAULIC_STREAM: u64 = 0x082EFA98EC4E6C89;
+/// Tag of the RNG stream of per-pixel grain.
+const GRAIN_STREAM: u64 = 0x452821E638D01377;
+
+/// An RNG stream independent of the octave gradients, so optional features that draw from it
+/// don't change the rest of the map.
+pub(crate) fn substream(seed: (u64, u64), tag: u64) -> Mwc256XXA64 {
+    Mwc256XXA64::new(seed.0 ^ tag, seed.1)
+}
+
+impl Octave {
+    /// Fills a gradient grid covering the map with cells of about `cell_size` pixels; `key`
+    /// tells grids apart for hashed gradients.
+    fn new(config: &HeightmapConfig, cell_size: f32, scale: f32, key: u64, rotation: Option<f64>,
+        rng: &mut impl Rng) -> Self {
+        let octave = Octave::build(config, cell_size, scale, rotation, |dims, (base_i, base_j)| {
+            let mut vecs = Array2::from_elem(dims, Point::default());
+            match (config.gradients, config.noise) {
+                (GradientSource::Hashed, _) => for ((x, y), v) in vecs.indexed_iter_mut() {
+                    let hash = lattice_hash(config.seed, key, base_i + x as i64, base_j + y as i64);
+                    *v = config.noise.hashed_point(hash, config.angle_table);
+                },
+                (GradientSource::Table, NoiseKind::Perlin | NoiseKind::Simplex) => {
+                    let perm = permutation(rng);
+                    for ((x, y), v) in vecs.indexed_iter_mut() {
+                        *v = table_gradient(&perm, x, y);
+                    }
+                }
+                _ => for v in vecs.iter_mut() {
+                    *v = config.noise.random_point(rng, config.angle_table);
+                },
+            }
+            vecs
+        });
+        octave.expect(GRID_TOO_LARGE)
+    }
+}
+
+impl Octave<HashedLattice> {
+    /// The grid [`Octave::new`] fills with hashed gradients, without filling it.
+    fn hashed(config: &HeightmapConfig, cell_size: f32, scale: f32, key: u64, rotation: Option<f64>) -> Self {
+        let (seed, noise, angle_table) = (config.seed, config.noise, config.angle_table);
+        let octave = Octave::build(config, cell_size, scale, rotation, |dims, base| {
+            let cells = (config.wrap_y.then_some(dims[0]), config.wrap_x.then_some(dims[1]));
+            HashedLattice { seed, key, noise, angle_table, base, cells }
+        });
+        octave.expect(GRID_TOO_LARGE)
+    }
+}
+
+impl<L> Octave<L> {
+    /// Lays out a grid covering the map with cells of about `cell_size` pixels, and gets its
+    /// points from `lattice`, given the grid's dimensions and the lattice position of its first
+    /// point. `None` if the cells are so small that the grid would have more than
+    /// [`MAX_POINTS_PER_PIXEL`] points per pixel of the map, or more than fit in memory.
+    fn build(config: &HeightmapConfig, cell_size: f32, scale: f32, rotation: Option<f64>,
+        lattice: impl FnOnce([usize; 2], (i64, i64)) -> L) -> Option<Self> {
+        let (width, height) = (config.width, config.height);
+        let mut cell_h = cell_size * config.feature_scale.1;
+        let mut cell_w = cell_size * config.feature_scale.0;
+
+        // a whole number of cells along wrapping axes, with no extra lattice line of gradients;
+        // `perlin` looks the last lattice line up from the first instead
+        let whole_cells = |dim: usize, cell: &mut f32| {
+            let cells = (dim as f32 / *cell).round().max(1.);
+            *cell = dim as f32 / cells;
+            (cells <= u32::MAX as f32).then_some(cells as usize)
+        };
+        let cells_h = if config.wrap_y { Some(whole_cells(height, &mut cell_h)?) } else { None };
+        let cells_w = if config.wrap_x { Some(whole_cells(width, &mut cell_w)?) } else { None };
+
+        // a stretched or rotated map covers the box around its transformed corners, which
+        // starts at the transformed origin plus the lowest corner
+        let transform = Transform::of(config, rotation);
+        let mut origin = (config.origin.1 as f64, config.origin.0 as f64);
+        let mut extent = (height as f32, width as f32);
+        let mut lowest = (0., 0.);
+        if let Some(transform) = transform {
+            let corners = [(0, 0), (height, 0), (0, width), (height, width)]
+                .map(|(i, j)| transform.apply(i as f64, j as f64));
+            let bounds = |axis: fn(&(f64, f64)) -> f64| {
+                corners.iter().map(axis).fold((f64::MAX, f64::MIN), |(lo, hi), p| (lo.min(p), hi.max(p)))
+            };
+            let ((lo_i, hi_i), (lo_j, hi_j)) = (bounds(|p| p.0), bounds(|p| p.1));
+            let (oi, oj) = transform.apply(origin.0, origin.1);
+            origin = (oi + lo_i, oj + lo_j);
+            extent = ((hi_i - lo_i) as f32, (hi_j - lo_j) as f32);
+            lowest = (lo_i, lo_j);
+        }
+
+        // the first lattice point of the grid, one cell before the origin for Worley's
+        // neighbour search, and how far past it the origin is; in f64 so distant tiles still
+        // line up
+        let offset = |origin: f64, cell: f32, wraps: bool| {
+            if config.gradients != GradientSource::Hashed || wraps { return (0, 0.); }
+            let p = origin / cell as f64;
+            (p.floor() as i64 - 1, (p - p.floor()) as f32 + 1.)
+        };
+        let (base_i, shift_i) = offset(origin.0, cell_h, config.wrap_y);
+        let (base_j, shift_j) = offset(origin.1, cell_w, config.wrap_x);
+
+        let [grid_h, grid_w] = config.noise.grid_dims(extent.0 / cell_h + shift_i, extent.1 / cell_w + shift_j)?;
+        let dims = [cells_h.unwrap_or(grid_h), cells_w.unwrap_or(grid_w)];
+        let pixels = (width + 2).checked_mul(height + 2)?;
+        if dims[0].checked_mul(dims[1])? > pixels.checked_mul(MAX_POINTS_PER_PIXEL)? {
+            return None;
+        }
+
+        let vecs = lattice(dims, (base_i, base_j));
+        let transform = transform.map(|t| (t, lowest));
+        Some(Octave { vecs, cell_h, cell_w, shift: (shift_i, shift_j), scale, transform })
+    }
+}
+
+impl<L: Lattice> Octave<L> {
+    /// Lattice coordinates of point `(i, j)`.
+    fn lattice(&self, i: f32, j: f32) -> (f32, f32) {
+        let (i, j) = match self.transform {
+            Some((transform, (lo_i, lo_j))) => {
+                let (i, j) = transform.apply(i as f64, j as f64);
+                ((i - lo_i) as f32, (j - lo_j) as f32)
+            }
+            None => (i, j),
+        };
+        (i / self.cell_h + self.shift.0, j / self.cell_w + self.shift.1)
+    }
+
+    fn sample(&self, config: &HeightmapConfig, i: f32, j: f32) -> f32 {
+        let (x, y) = self.lattice(i, j);
+        config.noise.sample(&self.vecs, x, y, config.fade)
+    }
+}
+
+/// Fills the gradient grids of every octave, coarsest first. They are kept for the whole pixel
+/// pass, so each grid is filled once and shared by all threads.
+fn octaves(config: &HeightmapConfig, rng: &mut impl Rng) -> Vec<Octave> {
+    if config.octave_seeds {
+        return config.levels()
+            .map(|(level, scale)| {
+                let mut rng = substream(config.seed, lattice_hash(config.seed, OCTAVE_SEED_KEY, level as i64, 0));
+                let (cell_size, rotation) = (config.cell_size(level), rotation(config, level));
+                Octave::new(config, cell_size, scale, level as u64, rotation, &mut rng)
+            })
+            .collect();
+    }
+    // grids of skipped coarse levels are still drawn, so the kept ones get the same gradients
+    if config.gradients != GradientSource::Hashed {
+        for level in (config.coarsest().saturating_add(1)..config.octaves).rev() {
+            Octave::new(config, config.cell_size(level), 0., level as u64, None, rng);
+        }
+    }
+    config.levels()
+        .map(|(level, scale)| {
+            Octave::new(config, config.cell_size(level), scale, level as u64, rotation(config, level), rng)
+        })
+        .collect()
+}
+
+/// An octave level whose gradient grid [`Octave::build`] won't lay out, if there is one, among
+/// the grids [`generate`] fills: the generated levels, the coarser ones drawn and skipped, and
+/// the warp fields.
+pub(crate) fn oversized_level(config: &HeightmapConfig) -> Option<usize> {
+    let fits = |level: usize, rotation| {
+        Octave::build(config, config.cell_size(level), 0., rotation, |_, _| ()).is_some()
+    };
+    // skipped levels and the warp fields aren't rotated
+    let mut unrotated = Vec::new();
+    if config.gradients != GradientSource::Hashed && !config.octave_seeds {
+        unrotated.extend(config.coarsest().saturating_add(1)..config.octaves);
+    }
+    if config.warp_strength != 0. {
+        unrotated.push(config.octaves.saturating_sub(1));
+    }
+    config.levels().map(|(level, _)| (level, rotation(config, level)))
+        .chain(unrotated.into_iter().map(|level| (level, None)))
+        .find(|&(level, rotation)| !fits(level, rotation))
+        .map(|(level, _)| level)
+}
+
+/// Brings a warped coordinate back onto the map, wrapping along wrapping axes and mirroring at
+/// the edges otherwise.
+fn fold(p: f32, dim: usize, wraps: bool) -> f32 {
+    let dim = dim as f32;
+    if wraps { return p.rem_euclid(dim); }
+
+    let max = (dim - 1.).max(0.);
+    let p = p.rem_euclid(max * 2.);
+    if p > max { max * 2. - p } else { p }
+}
+
+/// Where a map's randomness comes from.
+pub(crate) enum Source<'a> {
+    /// Independent streams of `config.seed`.
+    Seed,
+    /// One caller-supplied RNG that everything is drawn from in turn.
+    Rng(&'a mut dyn RngCore),
+}
+
+impl Source<'_> {
+    /// The RNG to draw the part of the map tagged `tag` from; tag 0 is the seed itself.
+    fn stream(&mut self, config: &HeightmapConfig, tag: u64) -> Box<dyn RngCore + '_> {
+        match self {
+            Source::Seed => Box::new(substream(config.seed, tag)),
+            Source::Rng(rng) => Box::new(&mut **rng),
+        }
+    }
+}
+
+/// The two displacement fields of domain warping, if it is enabled.
+fn warp_fields(config: &HeightmapConfig, source: &mut Source) -> Option<[Octave; 2]> {
+    (config.warp_strength != 0.).then(|| {
+        let mut rng = source.stream(config, WARP_STREAM);
+        let cell_size = config.cell_size(config.octaves.saturating_sub(1));
+        [0, 1].map(|k| Octave::new(config, cell_size, config.warp_strength, WARP_STREAM + k, None, &mut rng))
+    })
+}
+
+/// Where point `(pi, pj)` samples the octaves, after any warping.
+fn warped<L: Lattice>(config: &HeightmapConfig, warp: &Option<[Octave<L>; 2]>, pi: f32, pj: f32) -> (f32, f32) {
+    match warp {
+        Some([wi, wj]) => {
+            let (di, dj) = (wi.sample(config, pi, pj) * wi.scale, wj.sample(config, pi, pj) * wj.scale);
+            (fold(pi + di, config.height, config.wrap_y), fold(pj + dj, config.width, config.wrap_x))
+        }
+        None => (pi, pj),
+    }
+}
+
+/// Each octave's shaped noise on its own, coarsest first, multiplied by its amplitude if
+/// `scaled` is set.
+pub(crate) fn octave_layers(config: &HeightmapConfig, scaled: bool) -> Vec<Array2<f32>> {
+    let mut source = Source::Seed;
+    let octaves = octaves(config, &mut source.stream(config, 0));
+    let warp = warp_fields(config, &mut source);
+
+    octaves.iter().map(|o| {
+        let mut layer = Array2::from_elem([config.height, config.width], 0f32);
+        for_each_indexed(&mut layer, |(i, j), v| {
+            let (pi, pj) = warped(config, &warp, i as f32, j as f32);
+            *v = config.style.shape(o.sample(config, pi, pj)) * if scaled { o.scale } else { 1. };
+        });
+        layer
+    }).collect()
+}
+
+/// The offset [`warped`] moves each pixel by before any wrapping, as `(x, y)`.
+pub(crate) fn warp_offsets(config: &HeightmapConfig) -> Array2<(f32, f32)> {
+    let warp = warp_fields(config, &mut Source::Seed);
+    map_indexed(&Array2::from_elem([config.height, config.width], 0f32), |(i, j), _| match &warp {
+        Some([wi, wj]) => {
+            let (pi, pj) = (i as f32, j as f32);
+            (wj.sample(config, pi, pj) * wj.scale, wi.sample(config, pi, pj) * wi.scale)
+        }
+        None => (0., 0.),
+    })
+}
+
+/// The summed octaves at point `(pi, pj)`, normalized inline with `ScaleSum` and left as the raw
+/// sum otherwise.
+fn octave_sum<L: Lattice>(config: &HeightmapConfig, octaves: &[Octave<L>], warp: &Option<[Octave<L>; 2]>,
+    scale_sum: Accum, pi: f32, pj: f32) -> f32 {
+    let (pi, pj) = warped(config, warp, pi, pj);
+
+    let mut sum: Accum = 0.;
+    for o in octaves {
+        let n = config.style.shape(o.sample(config, pi, pj));
+        sum += n as Accum * o.scale as Accum;
+    }
+
+    match config.normalization {
+        Normalization::ScaleSum => config.style.normalize((sum / scale_sum) as f32),
+        Normalization::MinMax | Normalization::None | Normalization::Equalize => sum as f32,
+    }
+}
+
+/// [`octave_sum`] of Perlin noise without warping, along with its derivatives along the rows and
+/// columns.
+fn octave_sum_with_gradient(config: &HeightmapConfig, octaves: &[Octave], scale_sum: Accum, i: usize, j: usize)
+    -> (f32, f32, f32) {
+    let (pi, pj) = (i as f32, j as f32);
+
+    let mut sum: Accum = 0.;
+    let mut di: Accum = 0.;
+    let mut dj: Accum = 0.;
+    for o in octaves {
+        let (x, y) = o.lattice(pi, pj);
+        let (n, dx, dy) = perlin_with_deriv(&o.vecs, x, y, config.fade);
+        // chain rule through the shaping, the lattice scale and the transform
+        let slope = config.style.shape_slope(n) * o.scale;
+        sum += config.style.shape(n) as Accum * o.scale as Accum;
+        let (dx, dy) = (slope * dx / o.cell_h, slope * dy / o.cell_w);
+        let (dx, dy) = match o.transform {
+            Some((transform, _)) => {
+                let (dx, dy) = transform.apply_transposed(dx as f64, dy as f64);
+                (dx as f32, dy as f32)
+            }
+            None => (dx, dy),
+        };
+        di += dx as Accum;
+        dj += dy as Accum;
+    }
+
+    match config.normalization {
+        Normalization::ScaleSum => {
+            let slope = config.style.normalize(1.) - config.style.normalize(0.);
+            di *= slope as Accum / scale_sum;
+            dj *= slope as Accum / scale_sum;
+            (config.style.normalize((sum / scale_sum) as f32), di as f32, dj as f32)
+        }
+        Normalization::None => (sum as f32, di as f32, dj as f32),
+        Normalization::MinMax | Normalization::Equalize => unreachable!(),
+    }
+}
+
+/// `v`, the summed octaves at point `(i, j)` as [`octave_sum`] leaves them, raised by the
+/// config's bump if it has one. The bump is in the units of `ScaleSum` heights, so raw sums get
+/// it multiplied by `scale_sum`.
+fn bump(config: &HeightmapConfig, scale_sum: Accum, i: f32, j: f32, v: f32) -> f32 {
+    let Some((amplitude, power, (x, y))) = config.bump else { return v };
+    let d = |p: f32, centre: f32, w: usize| (p - centre * w as f32) / (w as f32 / 2.);
+    let lift = amplitude * (1. - d(i, y, config.height).hypot(d(j, x, config.width))).max(0.).powf(power);
+    match config.normalization {
+        Normalization::ScaleSum => v + lift,
+        Normalization::MinMax | Normalization::None | Normalization::Equalize => {
+            let sum: Accum = v as Accum + lift as Accum * scale_sum;
+            sum as f32
+        }
+    }
+}
+
+/// Gain and bias applied to a finished height `v`, which is then flipped if the config inverts
+/// the finished map, and put through its response curve.
+fn adjust(config: &HeightmapConfig, v: f32) -> f32 {
+    let v = v * config.gain + config.bias;
+    let v = if config.invert == Some(Invert::AfterIsland) { invert(v, config.clamp_range) } else { v };
+    match &config.curve {
+        Some(curve) => curve.apply(v, config.clamp_range),
+        None => v,
+    }
+}
+
+/// Whether [`adjust`] changes any height.
+fn adjusts(config: &HeightmapConfig) -> bool {
+    config.gain != 1. || config.bias != 0. || config.invert == Some(Invert::AfterIsland) || config.curve.is_some()
+}
+
+/// Any inversion, then the squircle blend, island falloff, edge fade and terracing of a
+/// normalized height `v` at point `(i, j)`.
+fn shape(config: &HeightmapConfig, i: f32, j: f32, mut v: f32) -> f32 {
+    let (width, height) = (config.width, config.height);
+    if config.invert == Some(Invert::BeforeIsland) {
+        v = -v;
+    }
+
+    // squircle adjustment
+    let d = |x: f32, w: usize| x / w as f32 * 2. - 1.;
+    let dis = (d(i, height).powi(4) + d(j, width).powi(4)) / 2.;
+    let r = interpolate_linear(1., -2.5, dis);
+
+    v = v * config.perlin_weight + r * (1. - config.perlin_weight);
+
+    if let Some(exponent) = config.island {
+        let dis = d(i, height).hypot(d(j, width));
+        let falloff = (1. - dis.powf(exponent)).max(0.);
+        v = (v + 1.) * falloff - 1.;
+    }
+
+    if let Some((margin, target)) = config.edge_fade {
+        let fade = |x: f32, w: usize, wraps: bool| {
+            let edge = x.min(w as f32 - 1. - x);
+            if wraps || edge >= margin as f32 { 1. } else { (1. - (edge.max(0.) / margin as f32 * PI).cos()) / 2. }
+        };
+        let weight = fade(i, height, config.wrap_y) * fade(j, width, config.wrap_x);
+        // exactly as it was away from the edges
+        if weight < 1. {
+            v = target + (v - target) * weight;
+        }
+    }
+
+    if let Some((levels, smoothness)) = config.terraces {
+        v = terrace(v, levels, smoothness);
+    }
+    v
+}
+
+pub(crate) fn generate(config: &HeightmapConfig, mut source: Source, progress: &(dyn Fn(f32) + Sync))
+    -> Array2<f32> {
+    // all gradients are generated up front, so rows can be filled in parallel
+    let octaves = octaves(config, &mut source.stream(config, 0));
+    let warp = warp_fields(config, &mut source);
+    let mut res = sum_map(config, &octaves, &warp, progress);
+
+    finish(config, &mut res, &mut source);
+    progress(1.);
+
+    res
+}
+
+/// The summed octaves at every pixel, reporting progress by rows.
+fn sum_map(config: &HeightmapConfig, octaves: &[Octave], warp: &Option<[Octave; 2]>,
+    progress: &(dyn Fn(f32) + Sync)) -> Array2<f32> {
+    let (width, height) = (config.width, config.height);
+    let scale_sum: Accum = octaves.iter().map(|o| o.scale as Accum).sum();
+
+    let mut res = Array2::from_elem([height, width], 0f32);
+    let rows_done = AtomicUsize::new(0);
+
+    let mask = config.region_mask.as_ref();
+    for_each_outer(&mut res, |i, mut row| {
+        for (j, v) in row.iter_mut().enumerate() {
+            // filled in by `finish`
+            if mask.is_some_and(|mask| !mask[[i, j]]) { continue; }
+            *v = octave_sum(config, octaves, warp, scale_sum, i as f32, j as f32);
+        }
+
+        // reports each whole percent of rows, leaving 100% for when erosion is done too
+        let done = rows_done.fetch_add(1, Ordering::Relaxed) + 1;
+        if done < height && done * 100 / height != (done - 1) * 100 / height {
+            progress(done as f32 / height as f32);
+        }
+    });
+    res
+}
+
+/// The gradient grids [`generate`] fills from the seed.
+pub(crate) fn gradient_grids(config: &HeightmapConfig) -> GradientGrids {
+    let mut source = Source::Seed;
+    let octaves = octaves(config, &mut source.stream(config, 0)).into_iter().map(|o| o.vecs).collect();
+    let warp = warp_fields(config, &mut source).map(|fields| fields.map(|o| o.vecs));
+    GradientGrids { octaves, warp }
+}
+
+/// [`generate`] with the octaves and warp fields taken from `grids` instead of filled from the
+/// seed, which erosion and grain still draw from.
+pub(crate) fn from_grids(config: &HeightmapConfig, grids: &GradientGrids) -> Result<Array2<f32>, GradientsError> {
+    let levels: Vec<_> = config.levels().collect();
+    let warps = if config.warp_strength != 0. { 2 } else { 0 };
+    let found = grids.octaves.len() + grids.warp.as_ref().map_or(0, |w| w.len());
+    if grids.octaves.len() != levels.len() || grids.warp.is_some() != (warps == 2) {
+        return Err(GradientsError::GridCount { expected: levels.len() + warps, found });
+    }
+
+    // the same layout as a filled grid, checked against the one in the file
+    let layout = |index: usize, cell_size: f32, scale: f32, rotation: Option<f64>, grid: &Array2<Point>| {
+        let mut expected = [0, 0];
+        let octave = Octave::build(config, cell_size, scale, rotation, |dims, _| {
+            expected = dims;
+            grid.clone()
+        }).expect(GRID_TOO_LARGE);
+        let expected = (expected[0], expected[1]);
+        if grid.dim() != expected {
+            return Err(GradientsError::GridSize { index, expected, found: grid.dim() });
+        }
+        Ok(octave)
+    };
+    let octaves = levels.iter().zip(&grids.octaves).enumerate()
+        .map(|(k, (&(level, scale), grid))| {
+            layout(k, config.cell_size(level), scale, rotation(config, level), grid)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let warp = match &grids.warp {
+        Some([wi, wj]) => {
+            let cell_size = config.cell_size(config.octaves.saturating_sub(1));
+            let (k, strength) = (octaves.len(), config.warp_strength);
+            Some([layout(k, cell_size, strength, None, wi)?, layout(k + 1, cell_size, strength, None, wj)?])
+        }
+        None => None,
+    };
+
+    let mut res = sum_map(config, &octaves, &warp, &|_| {});
+    finish(config, &mut res, &mut Source::Seed);
+    Ok(res)
+}
+
+/// The whole-map normalization of summed octaves, if `config` has one.
+fn normalize<D: Dimension>(config: &HeightmapConfig, map: &mut Array<f32, D>) {
+    match config.normalization {
+        Normalization::MinMax => stretch(map),
+        Normalization::Equalize => equalize(map),
+        Normalization::ScaleSum | Normalization::None => {}
+    }
+}
+
+/// Runs `f`, a whole-map normalization, over only the pixels of `map` inside `mask`, so the
+/// fill around a region doesn't count towards its range.
+pub(crate) fn within_region(mask: &Array2<bool>, map: &mut Array2<f32>, f: impl FnOnce(&mut Array1<f32>)) {
+    let mut inside: Array1<f32> = map.iter().zip(mask).filter_map(|(&v, &m)| m.then_some(v)).collect();
+    f(&mut inside);
+    for (v, n) in map.iter_mut().zip(mask).filter_map(|(v, &m)| m.then_some(v)).zip(inside) {
+        *v = n;
+    }
+}
+
+/// Sets the pixels outside the config's region mask, if it has one, to its fill height.
+pub(crate) fn fill_outside(config: &HeightmapConfig, res: &mut Array2<f32>) {
+    if let Some(mask) = &config.region_mask {
+        for_each_indexed(res, |(i, j), v| if !mask[[i, j]] { *v = config.region_fill; });
+    }
+}
+
+/// Everything after the octaves are summed: normalization of the whole map, shaping, erosion,
+/// gain and bias, inversion and grain, and the region mask's fill.
+fn finish(config: &HeightmapConfig, res: &mut Array2<f32>, source: &mut Source) {
+    if config.bump.is_some() {
+        let scale_sum: Accum = config.levels().map(|(_, scale)| scale as Accum).sum();
+        for_each_indexed(res, |(i, j), v| *v = bump(config, scale_sum, i as f32, j as f32, *v));
+    }
+    match &config.region_mask {
+        Some(mask) if matches!(config.normalization, Normalization::MinMax | Normalization::Equalize) => {
+            within_region(mask, res, |inside| normalize(config, inside))
+        }
+        _ => normalize(config, res),
+    }
+
+    for_each_indexed(res, |(i, j), v| *v = shape(config, i as f32, j as f32, *v));
+    fill_outside(config, res);
+
+    if let Some((iterations, talus)) = config.thermal_erosion {
+        thermal_erode(res, iterations, talus, (config.wrap_x, config.wrap_y));
+    }
+    if let Some((droplets, params)) = &config.hydraulic_erosion {
+        let wrap = (config.wrap_x, config.wrap_y);
+        hydraulic_erode(res, *droplets, params, wrap, &mut source.stream(config, HYDRAULIC_STREAM));
+    }
+    if adjusts(config) {
+        for_each(res, |v| *v = adjust(config, *v));
+    }
+    if config.grain > 0. {
+        // drawn in row-major order, so the result doesn't depend on the thread count
+        let mut rng = source.stream(config, GRAIN_STREAM);
+        for v in res.iter_mut() {
+            *v += rng.gen_range(-config.grain..config.grain);
+        }
+    }
+    // again, over anything erosion, gain or grain moved there
+    fill_outside(config, res);
+}
+
+/// A disk of terrain filling the middle of the map, sampled by angle and distance from the
+/// centre, with every pixel outside it set to `fill`.
+pub(crate) fn disk(config: &HeightmapConfig, fill: f32) -> Array2<f32> {
+    let (width, height) = (config.width, config.height);
+    let radius = width.min(height) as f32 / 2.;
+
+    // the octaves are laid out over a strip as long as the rim and as tall as the radius,
+    // wrapping along its length so the angles meet without a seam
+    let rim = ((radius * TAU).round() as usize).max(1);
+    let mut domain = config.clone().with_size(rim, (radius.ceil() as usize).max(1)).with_wrap(true, false);
+    domain.anisotropy = None;
+    domain.octave_rotation = false;
+    let mut source = Source::Seed;
+    let octaves = octaves(&domain, &mut source.stream(&domain, 0));
+    let warp = warp_fields(&domain, &mut source);
+    let scale_sum: Accum = octaves.iter().map(|o| o.scale as Accum).sum();
+
+    // distance from the centre and angle of a pixel's centre
+    let polar = |i: usize, j: usize| {
+        let (y, x) = (i as f32 + 0.5 - height as f32 / 2., j as f32 + 0.5 - width as f32 / 2.);
+        (y.hypot(x), y.atan2(x).rem_euclid(TAU))
+    };
+    let mut res = map_indexed(&Array2::from_elem([height, width], 0f32), |(i, j), _| {
+        // pixels past the rim repeat it until they are filled, so whole-map normalization only
+        // sees heights on the disk
+        let (r, angle) = polar(i, j);
+        octave_sum(&domain, &octaves, &warp, scale_sum, r.min(radius), angle / TAU * rim as f32)
+    });
+
+    // the disk doesn't wrap, whatever the config says
+    finish(&config.clone().with_wrap(false, false), &mut res, &mut source);
+    for_each_indexed(&mut res, |(i, j), v| if polar(i, j).0 > radius { *v = fill; });
+    res
+}
+
+/// The height [`generate`] gives point `(i, j)` of the map, from hashed gradients looked up as
+/// needed; at a pixel it is exactly that pixel's height.
+///
+/// Panics unless the gradients are hashed, with `ScaleSum` or `None` normalization and no
+/// erosion or grain, which all need the whole map.
+pub(crate) fn height_at(config: &HeightmapConfig, i: f32, j: f32) -> f32 {
+    assert!(config.gradients == GradientSource::Hashed, "single points need hashed gradients");
+    assert!(matches!(config.normalization, Normalization::ScaleSum | Normalization::None),
+        "{:?} normalization needs the whole map", config.normalization);
+    assert!(config.thermal_erosion.is_none() && config.hydraulic_erosion.is_none() && config.grain == 0.,
+        "erosion and grain need the whole map");
+    let pixel = (i.floor() as usize, j.floor() as usize);
+    if i >= 0. && j >= 0. && config.region_mask.as_ref().and_then(|mask| mask.get(pixel)) == Some(&false) {
+        return config.region_fill;
+    }
+
+    // a point off the map is sampled from a map moved over it, so its lattice coordinates stay
+    // positive; wrapping axes repeat instead. Warping folds every point back onto the map.
+    let (mut local, mut pi, mut pj) = (config.clone(), i, j);
+    if config.warp_strength == 0. {
+        let onto = |p: &mut f32, dim: usize, wraps: bool, origin: &mut i64| {
+            if wraps {
+                *p = p.rem_euclid(dim as f32);
+            } else if !(0. ..dim as f32).contains(p) {
+                *origin += p.floor() as i64;
+                *p -= p.floor();
+            }
+        };
+        onto(&mut pi, config.height, config.wrap_y, &mut local.origin.1);
+        onto(&mut pj, config.width, config.wrap_x, &mut local.origin.0);
+    }
+
+    let octaves: Vec<_> = local.levels()
+        .map(|(level, scale)| {
+            Octave::hashed(&local, local.cell_size(level), scale, level as u64, rotation(&local, level))
+        })
+        .collect();
+    let warp = (local.warp_strength != 0.).then(|| {
+        let cell_size = local.cell_size(local.octaves.saturating_sub(1));
+        [0, 1].map(|k| Octave::hashed(&local, cell_size, local.warp_strength, WARP_STREAM + k, None))
+    });
+    let scale_sum: Accum = octaves.iter().map(|o| o.scale as Accum).sum();
+
+    let sum = octave_sum(&local, &octaves, &warp, scale_sum, pi, pj);
+    adjust(config, shape(config, i, j, bump(config, scale_sum, i, j, sum)))
+}
+
+/// `base` plus `strength` times the summed and normalized octaves of `config`, which has the
+/// size of `base`.
+pub(crate) fn detail(config: &HeightmapConfig, base: &Array2<f32>, strength: f32) -> Array2<f32> {
+    let mut source = Source::Seed;
+    let octaves = octaves(config, &mut source.stream(config, 0));
+    let warp = warp_fields(config, &mut source);
+    let scale_sum: Accum = octaves.iter().map(|o| o.scale as Accum).sum();
+
+    let mut detail = map_indexed(base, |(i, j), _| {
+        let (pi, pj) = (i as f32, j as f32);
+        bump(config, scale_sum, pi, pj, octave_sum(config, &octaves, &warp, scale_sum, pi, pj))
+    });
+    normalize(config, &mut detail);
+    for_each_indexed(&mut detail, |(i, j), v| *v = base[[i, j]] + *v * strength);
+    detail
+}
+
+/// The map [`generate`] makes from `config.seed`, as successive strips of `rows` rows each (the
+/// last one possibly shorter), filling only one strip at a time.
+///
+/// Panics if `rows` is 0, or if `config` needs the whole map at once: for `MinMax` or
+/// `Equalize` normalization, or for erosion.
+pub(crate) fn strips(config: &HeightmapConfig, rows: usize) -> impl Iterator<Item = Array2<f32>> + '_ {
+    assert!(rows > 0, "strips need at least one row");
+    assert!(matches!(config.normalization, Normalization::ScaleSum | Normalization::None),
+        "{:?} normalization needs the whole map and can't be generated in strips", config.normalization);
+    assert!(config.thermal_erosion.is_none() && config.hydraulic_erosion.is_none(),
+        "erosion needs the whole map and can't be generated in strips");
+
+    let mut source = Source::Seed;
+    let octaves = octaves(config, &mut source.stream(config, 0));
+    let warp = warp_fields(config, &mut source);
+    let scale_sum: Accum = octaves.iter().map(|o| o.scale as Accum).sum();
+    // carried across strips, so grain is drawn in the same row-major order as by `generate`
+    let mut grain = substream(config.seed, GRAIN_STREAM);
+
+    let mut first = 0;
+    iter::from_fn(move || {
+        if first >= config.height { return None; }
+        let mut strip = Array2::from_elem([rows.min(config.height - first), config.width], 0f32);
+
+        let mask = config.region_mask.as_ref();
+        for_each_indexed(&mut strip, |(i, j), v| {
+            if mask.is_some_and(|mask| !mask[[first + i, j]]) { return; }
+            let (pi, pj) = ((first + i) as f32, j as f32);
+            let sum = bump(config, scale_sum, pi, pj, octave_sum(config, &octaves, &warp, scale_sum, pi, pj));
+            *v = adjust(config, shape(config, pi, pj, sum));
+        });
+        if config.grain > 0. {
+            for v in strip.iter_mut() {
+                *v += grain.gen_range(-config.grain..config.grain);
+            }
+        }
+        if let Some(mask) = mask {
+            for_each_indexed(&mut strip, |(i, j), v| if !mask[[first + i, j]] { *v = config.region_fill; });
+        }
+
+        first += strip.nrows();
+        Some(strip)
+    })
+}
+
+/// The map [`generate`] makes from `config.seed`, along with the exact derivatives of each
+/// height along the rows and columns.
+///
+/// Panics unless the noise is Perlin, with no warping, `ScaleSum` or `None` normalization, and
+/// none of the later stages except the squircle blend, gain, bias and inversion.
+pub(crate) fn generate_with_gradient(config: &HeightmapConfig) -> (Array2<f32>, Array2<(f32, f32)>) {
+    assert!(config.noise == NoiseKind::Perlin, "analytic gradients need Perlin noise");
+    assert!(config.warp_strength == 0., "analytic gradients can't follow domain warping");
+    assert!(matches!(config.normalization, Normalization::ScaleSum | Normalization::None),
+        "analytic gradients need scale-sum or no normalization");
+    assert!(config.bump.is_none() && config.island.is_none() && config.edge_fade.is_none()
+        && config.terraces.is_none() && config.thermal_erosion.is_none() && config.hydraulic_erosion.is_none()
+        && config.grain == 0. && config.region_mask.is_none(),
+        "analytic gradients can't follow the bump, island falloff, edge fade, terracing, erosion, grain or a \
+        region mask");
+    let (width, height) = (config.width, config.height);
+
+    let octaves = octaves(config, &mut Source::Seed.stream(config, 0));
+    let scale_sum: Accum = octaves.iter().map(|o| o.scale as Accum).sum();
+
+    let sums = map_indexed(&Array2::from_elem([height, width], 0f32), |(i, j), _| {
+        octave_sum_with_gradient(config, &octaves, scale_sum, i, j)
+    });
+
+    // the curve is applied once the gradients are taken, since they are scaled by its slope
+    let linear = HeightmapConfig { curve: None, ..config.clone() };
+    let mut res = sums.map(|&(v, ..)| v);
+    for_each_indexed(&mut res, |(i, j), v| *v = shape(config, i as f32, j as f32, *v));
+    if adjusts(&linear) {
+        for_each(&mut res, |v| *v = adjust(&linear, *v));
+    }
+
+    // the squircle's distance term is (d_i^4 + d_j^4) / 2, with each d running from -1 to 1
+    // across the map
+    let falloff = |x: usize, w: usize| {
+        let d = x as f32 / w as f32 * 2. - 1.;
+        (-2.5 - 1.) * 2. * d * d * d * 2. / w as f32
+    };
+    let mut gradient = Array2::from_shape_fn([height, width], |(i, j)| {
+        let (_, di, dj) = sums[[i, j]];
+        // inverting before the blend flips the noise, and after it everything
+        let (noise, all) = match config.invert {
+            Some(Invert::BeforeIsland) => (-1., 1.),
+            Some(Invert::AfterIsland) => (1., -1.),
+            None => (1., 1.),
+        };
+        let blend = |dn: f32, dr: f32| {
+            (dn * noise * config.perlin_weight + dr * (1. - config.perlin_weight)) * config.gain * all
+        };
+        (blend(di, falloff(i, height)), blend(dj, falloff(j, width)))
+    });
+
+    if let Some(curve) = &config.curve {
+        Zip::from(&mut res).and(&mut gradient).for_each(|v, (di, dj)| {
+            let slope = curve.slope(*v, config.clamp_range);
+            (*di, *dj) = (*di * slope, *dj * slope);
+            *v = curve.apply(*v, config.clamp_range);
+        });
+    }
+    (res, gradient)
+}
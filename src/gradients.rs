@@ -0,0 +1,142 @@
+use std::{error::Error, fmt, io::{self, Read, Write}};
+
+use ndarray::Array2;
+
+use crate::kernel::Point;
+
+/// Bytes every gradient file starts with.
+const MAGIC: &[u8; 8] = b"HMGRADS\n";
+/// Version of the layout after the magic bytes; files of any other version are rejected.
+const VERSION: u16 = 1;
+
+/// The gradient grids of every octave of a map and of its warp fields, as they are before any
+/// pixel is filled: what [`HeightmapConfig::gradient_grids`](crate::HeightmapConfig::gradient_grids)
+/// captures and [`HeightmapConfig::generate_from_grids`](crate::HeightmapConfig::generate_from_grids)
+/// generates from.
+///
+/// Saved to a file, they reproduce a map exactly however the RNG or platform trigonometry
+/// changes, since nothing has to be drawn again. The file holds the magic bytes `HMGRADS\n`,
+/// a little-endian `u16` version, the `u32` numbers of octave and warp grids, then each grid
+/// coarsest first as its `u32` rows and columns and its points in row-major order as pairs of
+/// little-endian `f32`s.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GradientGrids {
+    pub(crate) octaves: Vec<Array2<Point>>,
+    pub(crate) warp: Option<[Array2<Point>; 2]>,
+}
+
+impl GradientGrids {
+    /// All grids, octaves first, then the warp fields.
+    pub(crate) fn grids(&self) -> impl Iterator<Item = &Array2<Point>> {
+        self.octaves.iter().chain(self.warp.iter().flatten())
+    }
+
+    /// Writes the grids in the versioned format described above, a few bytes at a time, so wrap
+    /// `writer` in a [`BufWriter`](std::io::BufWriter).
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&VERSION.to_le_bytes())?;
+        let warps = if self.warp.is_some() { 2u32 } else { 0 };
+        writer.write_all(&(self.octaves.len() as u32).to_le_bytes())?;
+        writer.write_all(&warps.to_le_bytes())?;
+        for grid in self.grids() {
+            let (rows, cols) = grid.dim();
+            writer.write_all(&(rows as u32).to_le_bytes())?;
+            writer.write_all(&(cols as u32).to_le_bytes())?;
+            for &(x, y) in grid.iter() {
+                writer.write_all(&x.to_le_bytes())?;
+                writer.write_all(&y.to_le_bytes())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads grids [`write`](Self::write) wrote, rejecting anything else and files of another
+    /// version of the format.
+    pub fn read<R: Read>(mut reader: R) -> Result<Self, GradientsError> {
+        let mut magic = [0; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC { return Err(GradientsError::NotGradients); }
+        let version = u16::from_le_bytes(read_bytes(&mut reader)?);
+        if version != VERSION { return Err(GradientsError::Version(version)); }
+
+        let read_u32 = |reader: &mut R| Ok::<_, io::Error>(u32::from_le_bytes(read_bytes(reader)?) as usize);
+        let (octaves, warps) = (read_u32(&mut reader)?, read_u32(&mut reader)?);
+        if warps != 0 && warps != 2 { return Err(GradientsError::NotGradients); }
+
+        let mut grids = Vec::with_capacity(octaves + warps);
+        for _ in 0..octaves + warps {
+            let (rows, cols) = (read_u32(&mut reader)?, read_u32(&mut reader)?);
+            // read point by point rather than trusting the header with one allocation
+            let mut points = Vec::new();
+            for _ in 0..rows.checked_mul(cols).ok_or(GradientsError::NotGradients)? {
+                let x = f32::from_le_bytes(read_bytes(&mut reader)?);
+                let y = f32::from_le_bytes(read_bytes(&mut reader)?);
+                points.push((x, y));
+            }
+            grids.push(Array2::from_shape_vec([rows, cols], points).expect("one point per grid cell"));
+        }
+
+        let warp = (warps == 2).then(|| {
+            let (j, i) = (grids.pop().unwrap(), grids.pop().unwrap());
+            [i, j]
+        });
+        Ok(GradientGrids { octaves: grids, warp })
+    }
+}
+
+fn read_bytes<const N: usize>(reader: &mut impl Read) -> io::Result<[u8; N]> {
+    let mut buf = [0; N];
+    reader.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+/// Why gradient grids couldn't be read or didn't fit a configuration.
+#[derive(Debug)]
+pub enum GradientsError {
+    /// Reading failed, or the file ended early.
+    Io(io::Error),
+    /// The data isn't a gradient file.
+    NotGradients,
+    /// The file is of another version of the format.
+    Version(u16),
+    /// There are `found` grids, octaves and warp fields together, where the configuration needs
+    /// `expected`.
+    GridCount { expected: usize, found: usize },
+    /// Grid `index`, counting octaves coarsest first and then the warp fields, is `found` (rows,
+    /// columns) where the configuration lays out `expected`.
+    GridSize { index: usize, expected: (usize, usize), found: (usize, usize) },
+}
+
+impl fmt::Display for GradientsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GradientsError::Io(e) => write!(f, "couldn't read gradients: {e}"),
+            GradientsError::NotGradients => write!(f, "not a gradient file"),
+            GradientsError::Version(v) => {
+                write!(f, "gradient file version {v} isn't supported, only version {VERSION}")
+            }
+            GradientsError::GridCount { expected, found } => {
+                write!(f, "the configuration needs {expected} gradient grids, found {found}")
+            }
+            GradientsError::GridSize { index, expected, found } => write!(f,
+                "gradient grid {index} is {} × {} but the configuration needs {} × {}",
+                found.1, found.0, expected.1, expected.0),
+        }
+    }
+}
+
+impl Error for GradientsError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            GradientsError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for GradientsError {
+    fn from(e: io::Error) -> Self {
+        GradientsError::Io(e)
+    }
+}
@@ -0,0 +1,19 @@
+use std::io::{BufRead, Seek};
+
+use image::{io::Reader, ImageFormat, ImageResult};
+use ndarray::Array2;
+
+/// Reads a grayscale PNG of any bit depth as heights, with the lowest possible sample at `-1`
+/// and the highest at `1`, the way 16-bit [`write_png`](crate::write_png) output maps them.
+/// Colour images are converted to their luma.
+///
+/// 8-bit [`write_png`](crate::write_png) output only uses the samples `0..=56`, so it reads
+/// back squeezed towards `-1`.
+pub fn read_png<R: BufRead + Seek>(reader: R) -> ImageResult<Array2<f32>> {
+    let image = Reader::with_format(reader, ImageFormat::Png).decode()?.into_luma16();
+    let (width, height) = image.dimensions();
+    let max = u16::MAX as f32;
+    Ok(Array2::from_shape_fn([height as usize, width as usize], |(i, j)| {
+        image.get_pixel(j as u32, i as u32).0[0] as f32 / max * 2. - 1.
+    }))
+}
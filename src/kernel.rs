@@ -0,0 +1,580 @@
+#[cfg(feature = "std")]
+use std::sync::OnceLock;
+
+use core::f32::consts::TAU;
+
+pub(crate) type Point = (f32, f32);
+
+/// The noise function evaluated for each octave.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "config-file", derive(serde::Deserialize), serde(rename_all = "kebab-case"))]
+pub enum NoiseKind {
+    /// Classic gradient noise on a square grid.
+    #[default]
+    Perlin,
+    /// Gradient noise on a skewed triangular grid, which avoids Perlin's axis-aligned artifacts.
+    Simplex,
+    /// Cellular noise: distances to feature points scattered one per grid cell.
+    Worley(WorleyMode),
+    /// A random value per lattice point, eased between with the [`Fade`] curve: cheaper than
+    /// Perlin and blobbier, which suits clouds and clutter masks.
+    Value,
+}
+
+/// Which feature point distances [`NoiseKind::Worley`] is built from.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "config-file", derive(serde::Deserialize), serde(rename_all = "kebab-case"))]
+pub enum WorleyMode {
+    /// Distance to the nearest point, giving rounded cells.
+    #[default]
+    F1,
+    /// Distance to the second nearest point, giving faceted, crystal-like cells.
+    F2,
+    /// Difference between the two, giving ridges along cell borders like cracked earth.
+    F2MinusF1,
+}
+
+impl NoiseKind {
+    /// Derives the value of one grid point from a [`lattice_hash`], distributed like
+    /// `random_point`.
+    pub(crate) fn hashed_point(self, hash: u64, table: bool) -> Point {
+        // two independent 24-bit fractions
+        let unit = |bits: u64| (bits & 0xFF_FFFF) as f32 / (1 << 24) as f32;
+        match self {
+            NoiseKind::Perlin | NoiseKind::Simplex => unit_vector(unit(hash), table),
+            NoiseKind::Worley(_) => (unit(hash), unit(hash >> 24)),
+            NoiseKind::Value => (unit(hash) * 2. - 1., 0.),
+        }
+    }
+
+    pub(crate) fn sample(self, src: &impl Lattice, x: f32, y: f32, fade: Fade) -> f32 {
+        match self {
+            NoiseKind::Perlin => perlin(src, x, y, fade),
+            NoiseKind::Simplex => simplex(src, x, y),
+            NoiseKind::Worley(mode) => worley(src, x, y, mode),
+            NoiseKind::Value => value_noise(src, x, y, fade),
+        }
+    }
+}
+
+/// Number of evenly spaced directions gradients are rounded to for
+/// [`HeightmapConfig::angle_table`](crate::HeightmapConfig::angle_table).
+const ANGLE_STEPS: usize = 4096;
+
+/// The unit vector `k` of [`ANGLE_STEPS`] steps around the circle.
+fn angle(k: usize) -> Point {
+    sin_cos(k as f32 / ANGLE_STEPS as f32 * TAU)
+}
+
+/// [`angle`] `k`, worked out once for all of them and kept in a table.
+#[cfg(feature = "std")]
+fn table_angle(k: usize) -> Point {
+    static TABLE: OnceLock<Vec<Point>> = OnceLock::new();
+    TABLE.get_or_init(|| (0..ANGLE_STEPS).map(angle).collect())[k]
+}
+
+/// [`angle`] `k`; there's nowhere to keep a table without the standard library, so it's worked
+/// out each time.
+#[cfg(not(feature = "std"))]
+fn table_angle(k: usize) -> Point {
+    angle(k)
+}
+
+/// The `(sin, cos)` unit vector `turns` of the way around the circle, for `turns` in `0..1`;
+/// rounded down to the nearest of [`ANGLE_STEPS`] angles from a table if `table` is set.
+pub(crate) fn unit_vector(turns: f32, table: bool) -> Point {
+    if table { table_angle((turns * ANGLE_STEPS as f32) as usize % ANGLE_STEPS) }
+    else { sin_cos(turns * TAU) }
+}
+
+#[cfg(feature = "std")]
+fn sin_cos(x: f32) -> Point {
+    x.sin_cos()
+}
+
+#[cfg(not(feature = "std"))]
+fn sin_cos(x: f32) -> Point {
+    libm::sincosf(x)
+}
+
+#[cfg(feature = "std")]
+fn sqrt(x: f32) -> f32 {
+    x.sqrt()
+}
+
+#[cfg(not(feature = "std"))]
+fn sqrt(x: f32) -> f32 {
+    libm::sqrtf(x)
+}
+
+#[cfg(feature = "std")]
+fn powi(x: f32, n: i32) -> f32 {
+    x.powi(n)
+}
+
+#[cfg(not(feature = "std"))]
+fn powi(x: f32, n: i32) -> f32 {
+    libm::powf(x, n as f32)
+}
+
+/// The 8 unit gradients [`GradientSource::Table`](crate::GradientSource::Table) picks from, one
+/// every 45°.
+const TABLE_GRADIENTS: [Point; 8] = {
+    use core::f32::consts::FRAC_1_SQRT_2 as D;
+    [(1., 0.), (D, D), (0., 1.), (-D, D), (-1., 0.), (-D, -D), (0., -1.), (D, -D)]
+};
+
+/// The SplitMix64 finalizer.
+fn mix(mut z: u64) -> u64 {
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+    z ^ (z >> 31)
+}
+
+/// A hash of lattice point `(x, y)` of the grid `key` under `seed`, for
+/// [`GradientSource::Hashed`](crate::GradientSource::Hashed).
+pub(crate) fn lattice_hash(seed: (u64, u64), key: u64, x: i64, y: i64) -> u64 {
+    [seed.1, key, x as u64, y as u64].into_iter().fold(mix(seed.0), |h, v| mix(h ^ v))
+}
+
+/// The gradient of lattice point `(x, y)` under a permutation table.
+pub(crate) fn table_gradient(perm: &[u8; 256], x: usize, y: usize) -> Point {
+    let hash = perm[(perm[x & 255] as usize + y) & 255];
+    TABLE_GRADIENTS[hash as usize & 7]
+}
+
+/// The gradients or feature points the 2D noises are built from, one per lattice point.
+pub(crate) trait Lattice {
+    /// The value at lattice point `(ix, iy)`.
+    fn at(&self, ix: usize, iy: usize) -> Point;
+    /// The value at lattice point `(ix, iy)`, which may be one before the first.
+    fn at_signed(&self, ix: isize, iy: isize) -> Point;
+}
+
+/// The hashed gradients of one grid, worked out as they are looked up instead of stored, for
+/// sampling a few points without filling a grid.
+pub(crate) struct HashedLattice {
+    pub(crate) seed: (u64, u64),
+    pub(crate) key: u64,
+    pub(crate) noise: NoiseKind,
+    pub(crate) angle_table: bool,
+    /// Lattice position of the grid's first point.
+    pub(crate) base: (i64, i64),
+    /// The number of cells along each wrapping axis, which the lattice repeats after.
+    pub(crate) cells: (Option<usize>, Option<usize>),
+}
+
+impl Lattice for HashedLattice {
+    fn at(&self, ix: usize, iy: usize) -> Point {
+        self.at_signed(ix as isize, iy as isize)
+    }
+
+    fn at_signed(&self, ix: isize, iy: isize) -> Point {
+        let wrapped = |p: isize, cells: Option<usize>| cells.map_or(p, |n| p.rem_euclid(n as isize)) as i64;
+        let (x, y) = (self.base.0 + wrapped(ix, self.cells.0), self.base.1 + wrapped(iy, self.cells.1));
+        self.noise.hashed_point(lattice_hash(self.seed, self.key, x, y), self.angle_table)
+    }
+}
+
+/// A grid of gradients or Worley feature points the caller keeps, `cols` to a row in row-major
+/// order, such as one grid of a gradient file; points past either end wrap around.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LatticeSlice<'a> {
+    points: &'a [Point],
+    rows: usize,
+    cols: usize,
+}
+
+impl<'a> LatticeSlice<'a> {
+    /// Panics if `points` is empty or doesn't fill whole rows of `cols`.
+    pub fn new(points: &'a [(f32, f32)], cols: usize) -> Self {
+        assert!(cols > 0 && !points.is_empty() && points.len().is_multiple_of(cols), "the points must fill whole rows");
+        Self { points, rows: points.len() / cols, cols }
+    }
+
+    /// Noise of `kind` at lattice position `(x, y)`, `x` along the columns of the grid and `y`
+    /// down its rows, both at least 0.
+    pub fn sample(&self, kind: NoiseKind, x: f32, y: f32, fade: Fade) -> f32 {
+        kind.sample(self, y, x, fade)
+    }
+}
+
+impl Lattice for LatticeSlice<'_> {
+    fn at(&self, ix: usize, iy: usize) -> Point {
+        self.points[ix % self.rows * self.cols + iy % self.cols]
+    }
+
+    fn at_signed(&self, ix: isize, iy: isize) -> Point {
+        let (ix, iy) = (ix.rem_euclid(self.rows as isize), iy.rem_euclid(self.cols as isize));
+        self.points[ix as usize * self.cols + iy as usize]
+    }
+}
+
+/// Looks up a lattice point's gradient.
+fn gradient_at(grid: &impl Lattice, ix: usize, iy: usize) -> Point {
+    grid.at(ix, iy)
+}
+
+fn dot_grid_gradient(grid: &impl Lattice, ix: usize, iy: usize, x: f32, y: f32) -> f32 {
+    let gradient = gradient_at(grid, ix, iy);
+
+    let dx = x - ix as f32;
+    let dy = y - iy as f32;
+
+    dx * gradient.0 + dy * gradient.1
+}
+
+fn interpolate(a0: f32, a1: f32, w: f32) -> f32 {
+    (a1 - a0) * (3. - w * 2.) * w * w + a0
+}
+
+pub(crate) fn interpolate_linear(a0: f32, a1: f32, w: f32) -> f32 {
+    (a1 - a0) * w + a0
+}
+
+/// The easing curve Perlin noise interpolates lattice values with.
+#[derive(Clone, Copy, Debug, Default)]
+#[cfg_attr(feature = "config-file", derive(serde::Deserialize), serde(rename_all = "kebab-case"))]
+pub enum Fade {
+    /// No easing; fastest, but the grid shows through as visible creases, which is fine for
+    /// quick previews.
+    Linear,
+    /// Smoothstep, `3w² - 2w³`; its second derivative jumps at cell borders, which can show up
+    /// as faint creases in normal maps.
+    #[default]
+    Cubic,
+    /// Perlin's improved fade, `6w⁵ - 15w⁴ + 10w³`, which is smooth to the second derivative.
+    Quintic,
+    /// Any curve from the fraction of the way across a cell, in `0..1`, to the weight of the
+    /// far lattice point; it should map 0 to 0 and 1 to 1 for the noise to stay continuous.
+    ///
+    /// ```
+    /// use heightmap_gen::{Fade, Fbm};
+    ///
+    /// let sine = Fade::Custom(|w| (1. - (w * std::f32::consts::PI).cos()) / 2.);
+    /// let mut map = [0f32; 64 * 64];
+    /// Fbm::default().with_fade(sine).fill(&mut map, 64);
+    /// ```
+    #[cfg_attr(feature = "config-file", serde(skip))]
+    Custom(fn(f32) -> f32),
+}
+
+impl PartialEq for Fade {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            // by address, so copies of one curve can compare unequal across codegen units
+            (Fade::Custom(a), Fade::Custom(b)) => core::ptr::fn_addr_eq(*a, *b),
+            _ => core::mem::discriminant(self) == core::mem::discriminant(other),
+        }
+    }
+}
+
+impl Eq for Fade {}
+
+impl Fade {
+    pub(crate) fn interpolate(self, a0: f32, a1: f32, w: f32) -> f32 {
+        match self {
+            Fade::Linear => interpolate_linear(a0, a1, w),
+            Fade::Cubic => interpolate(a0, a1, w),
+            Fade::Quintic => interpolate_linear(a0, a1, w * w * w * (w * (w * 6. - 15.) + 10.)),
+            Fade::Custom(fade) => interpolate_linear(a0, a1, fade(w)),
+        }
+    }
+
+    /// Slope of the curve at `w`; that of a custom curve is estimated by differences.
+    fn derivative(self, w: f32) -> f32 {
+        match self {
+            Fade::Linear => 1.,
+            Fade::Cubic => 6. * w * (1. - w),
+            Fade::Quintic => 30. * w * w * (w - 1.) * (w - 1.),
+            Fade::Custom(fade) => {
+                const H: f32 = 1e-3;
+                let (lo, hi) = ((w - H).max(0.), (w + H).min(1.));
+                (fade(hi) - fade(lo)) / (hi - lo)
+            }
+        }
+    }
+}
+
+pub(crate) fn perlin(src: &impl Lattice, x: f32, y: f32, fade: Fade) -> f32 {
+    let x0 = x as usize;
+    let y0 = y as usize;
+    let x1 = x0 + 1;
+    let y1 = y0 + 1;
+
+    let sx = x - x0 as f32;
+    let sy = y - y0 as f32;
+
+    let n0 = dot_grid_gradient(src, x0, y0, x, y);
+    let n1 = dot_grid_gradient(src, x1, y0, x, y);
+    let ix0 = fade.interpolate(n0, n1, sx);
+
+    let n0 = dot_grid_gradient(src, x0, y1, x, y);
+    let n1 = dot_grid_gradient(src, x1, y1, x, y);
+    let ix1 = fade.interpolate(n0, n1, sx);
+
+    fade.interpolate(ix0, ix1, sy)
+}
+
+/// Value noise: the values of the four surrounding lattice points, kept in the first component
+/// of each, eased between like [`perlin`] eases its dot products and scaled to about its range.
+pub(crate) fn value_noise(src: &impl Lattice, x: f32, y: f32, fade: Fade) -> f32 {
+    let (x0, y0) = (x as usize, y as usize);
+    let (sx, sy) = (x - x0 as f32, y - y0 as f32);
+
+    let ix0 = fade.interpolate(gradient_at(src, x0, y0).0, gradient_at(src, x0 + 1, y0).0, sx);
+    let ix1 = fade.interpolate(gradient_at(src, x0, y0 + 1).0, gradient_at(src, x0 + 1, y0 + 1).0, sx);
+    fade.interpolate(ix0, ix1, sy) * NOISE_AMPLITUDE
+}
+
+/// [`perlin`] along with its partial derivatives along `x` and `y`, worked out from the
+/// gradients and the fade curve rather than by differences. The value is exactly `perlin`'s.
+pub(crate) fn perlin_with_deriv(src: &impl Lattice, x: f32, y: f32, fade: Fade) -> (f32, f32, f32) {
+    let (x0, y0) = (x as usize, y as usize);
+    let (sx, sy) = (x - x0 as f32, y - y0 as f32);
+    let (u, du) = (fade.interpolate(0., 1., sx), fade.derivative(sx));
+    let (v, dv) = (fade.interpolate(0., 1., sy), fade.derivative(sy));
+
+    // the value and its derivatives along each lattice line, then between the two lines
+    let edge = |iy: usize| {
+        let (g0, g1) = (gradient_at(src, x0, iy), gradient_at(src, x0 + 1, iy));
+        let (n0, n1) = (dot_grid_gradient(src, x0, iy, x, y), dot_grid_gradient(src, x0 + 1, iy, x, y));
+        let dx = g0.0 + (g1.0 - g0.0) * u + (n1 - n0) * du;
+        let dy = g0.1 + (g1.1 - g0.1) * u;
+        (fade.interpolate(n0, n1, sx), dx, dy)
+    };
+    let (ix0, dx0, dy0) = edge(y0);
+    let (ix1, dx1, dy1) = edge(y0 + 1);
+
+    let dx = dx0 + (dx1 - dx0) * v;
+    let dy = dy0 + (dy1 - dy0) * v + (ix1 - ix0) * dv;
+    (fade.interpolate(ix0, ix1, sy), dx, dy)
+}
+
+/// How each octave's noise is shaped before it is added to the sum.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "config-file", derive(serde::Deserialize), serde(rename_all = "kebab-case"))]
+pub enum NoiseStyle {
+    /// Plain fractal Brownian motion: the noise is summed as-is.
+    #[default]
+    Fbm,
+    /// `1 - abs(noise)`, giving sharp crests along the noise's zero crossings; squaring it
+    /// narrows the crests further.
+    Ridged { squared: bool },
+    /// `abs(noise) * 2 - 1`, giving rounded hills with creased valleys.
+    Billow,
+}
+
+/// Approximate largest magnitude of a single octave of noise.
+pub(crate) const NOISE_AMPLITUDE: f32 = core::f32::consts::FRAC_1_SQRT_2;
+
+impl NoiseStyle {
+    pub(crate) fn shape(self, n: f32) -> f32 {
+        match self {
+            NoiseStyle::Fbm => n,
+            NoiseStyle::Ridged { squared } => {
+                let r = 1. - n.abs();
+                if squared { r * r } else { r }
+            }
+            NoiseStyle::Billow => n.abs() * 2. - 1.,
+        }
+    }
+
+    /// Slope of [`shape`](Self::shape) at `n`.
+    pub(crate) fn shape_slope(self, n: f32) -> f32 {
+        match self {
+            NoiseStyle::Fbm => 1.,
+            NoiseStyle::Ridged { squared } => if squared { -2. * (1. - n.abs()) * n.signum() } else { -n.signum() },
+            NoiseStyle::Billow => n.signum() * 2.,
+        }
+    }
+
+    /// Linearly maps a weighted average of shaped octaves onto the range of unshaped noise,
+    /// so every style is centred on zero like [`NoiseStyle::Fbm`].
+    pub(crate) fn normalize(self, v: f32) -> f32 {
+        let (lo, hi) = match self {
+            NoiseStyle::Fbm => return v,
+            NoiseStyle::Ridged { squared } => {
+                let lo = 1. - NOISE_AMPLITUDE;
+                if squared { (lo * lo, 1.) } else { (lo, 1.) }
+            }
+            NoiseStyle::Billow => (-1., NOISE_AMPLITUDE * 2. - 1.),
+        };
+        ((v - lo) / (hi - lo) * 2. - 1.) * NOISE_AMPLITUDE
+    }
+}
+
+/// `(sqrt(3) - 1) / 2`, skews input space onto the simplex lattice.
+pub(crate) const SIMPLEX_F2: f32 = 0.366_025_42;
+/// `(3 - sqrt(3)) / 6`, unskews lattice points back into input space.
+const SIMPLEX_G2: f32 = 0.211_324_87;
+
+fn simplex_corner(gradient: Point, dx: f32, dy: f32) -> f32 {
+    let t = 0.5 - dx * dx - dy * dy;
+    if t <= 0. { return 0.; }
+
+    let t2 = t * t;
+    t2 * t2 * (dx * gradient.0 + dy * gradient.1)
+}
+
+pub(crate) fn simplex(src: &impl Lattice, x: f32, y: f32) -> f32 {
+    let s = (x + y) * SIMPLEX_F2;
+    let i0 = (x + s) as usize;
+    let j0 = (y + s) as usize;
+
+    let t = (i0 + j0) as f32 * SIMPLEX_G2;
+    let x0 = x - (i0 as f32 - t);
+    let y0 = y - (j0 as f32 - t);
+
+    // which of the two triangles of the skewed cell we are in
+    let (i1, j1) = if x0 > y0 { (1, 0) } else { (0, 1) };
+
+    let x1 = x0 - i1 as f32 + SIMPLEX_G2;
+    let y1 = y0 - j1 as f32 + SIMPLEX_G2;
+    let x2 = x0 - 1. + 2. * SIMPLEX_G2;
+    let y2 = y0 - 1. + 2. * SIMPLEX_G2;
+
+    let n0 = simplex_corner(gradient_at(src, i0, j0), x0, y0);
+    let n1 = simplex_corner(gradient_at(src, i0 + i1, j0 + j1), x1, y1);
+    let n2 = simplex_corner(gradient_at(src, i0 + 1, j0 + 1), x2, y2);
+
+    // scales the output to about the same range as `perlin`
+    (n0 + n1 + n2) * 70.
+}
+
+pub(crate) fn worley(points: &impl Lattice, x: f32, y: f32, mode: WorleyMode) -> f32 {
+    let cx = x as isize;
+    let cy = y as isize;
+
+    // squared distances to the nearest and second nearest points in the surrounding cells;
+    // a neighbour before the first cell wraps to the last one, like the gradient lookups
+    let mut d1 = f32::INFINITY;
+    let mut d2 = f32::INFINITY;
+    for i in cx - 1..=cx + 1 { for j in cy - 1..=cy + 1 {
+        let p = points.at_signed(i, j);
+        let dx = i as f32 + p.0 - x;
+        let dy = j as f32 + p.1 - y;
+        let d = dx * dx + dy * dy;
+        if d < d1 { d2 = d1; d1 = d; } else if d < d2 { d2 = d; }
+    }}
+
+    let (d1, d2) = (sqrt(d1), sqrt(d2));
+    // maps the typical range of each distance onto that of the gradient noises
+    let (v, lo, hi) = match mode {
+        WorleyMode::F1 => (d1, 0., 1.),
+        WorleyMode::F2 => (d2, 0.2, 1.4),
+        WorleyMode::F2MinusF1 => (d2 - d1, 0., 1.),
+    };
+    ((v.clamp(lo, hi) - lo) / (hi - lo) * 2. - 1.) * NOISE_AMPLITUDE
+}
+
+/// Octaves of noise with hashed gradients summed one point at a time without allocating, which
+/// is all the crate builds without the `std` feature. Each point is, up to rounding, what a
+/// `HeightmapConfig` with the same settings generates there with a `perlin_weight` of 1 and
+/// nothing else changed.
+///
+/// ```
+/// use heightmap_gen::Fbm;
+///
+/// let mut map = [0f32; 64 * 48];
+/// Fbm::default().with_octaves(6).fill(&mut map, 64);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Fbm {
+    pub seed: (u64, u64),
+    pub octaves: usize,
+    /// Fraction of amplitude each octave loses relative to the next coarser one.
+    pub attenuation: f32,
+    /// How much larger the cells of each octave are than those of the next finer one.
+    pub lacunarity: f32,
+    pub noise: NoiseKind,
+    pub fade: Fade,
+    pub style: NoiseStyle,
+}
+
+impl Default for Fbm {
+    fn default() -> Self {
+        Self {
+            seed: (0x243F6A8885A308D3, 0x13198A2E03707344),
+            octaves: 8,
+            attenuation: 2. / 3.,
+            lacunarity: 2.,
+            noise: NoiseKind::Perlin,
+            fade: Fade::Cubic,
+            style: NoiseStyle::Fbm,
+        }
+    }
+}
+
+impl Fbm {
+    pub fn with_seed(mut self, seed: (u64, u64)) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    pub fn with_octaves(mut self, octaves: usize) -> Self {
+        self.octaves = octaves;
+        self
+    }
+
+    pub fn with_attenuation(mut self, attenuation: f32) -> Self {
+        self.attenuation = attenuation;
+        self
+    }
+
+    pub fn with_lacunarity(mut self, lacunarity: f32) -> Self {
+        self.lacunarity = lacunarity;
+        self
+    }
+
+    pub fn with_noise(mut self, noise: NoiseKind) -> Self {
+        self.noise = noise;
+        self
+    }
+
+    pub fn with_fade(mut self, fade: Fade) -> Self {
+        self.fade = fade;
+        self
+    }
+
+    pub fn with_style(mut self, style: NoiseStyle) -> Self {
+        self.style = style;
+        self
+    }
+
+    /// The summed octaves at row `i` and column `j`, at least 0 each.
+    ///
+    /// Panics if there are no `octaves` to sum.
+    pub fn sample(&self, i: f32, j: f32) -> f32 {
+        assert!(self.octaves > 0, "Fbm needs at least one octave");
+        let (mut sum, mut scale_sum, mut scale) = (0., 0., 1.);
+        for level in (0..self.octaves).rev() {
+            // the grid starts a cell before the first pixel, as for maps at the origin
+            let cell = powi(self.lacunarity, level as i32);
+            let lattice = HashedLattice {
+                seed: self.seed,
+                key: level as u64,
+                noise: self.noise,
+                angle_table: false,
+                base: (-1, -1),
+                cells: (None, None),
+            };
+            let n = self.style.shape(self.noise.sample(&lattice, i / cell + 1., j / cell + 1., self.fade));
+            sum += n * scale;
+            scale_sum += scale;
+            scale *= 1. - self.attenuation;
+        }
+        self.style.normalize(sum / scale_sum)
+    }
+
+    /// Fills `out` with a map `width` pixels wide, in row-major order.
+    ///
+    /// Panics if `out` doesn't hold whole rows of `width`, or as [`sample`](Self::sample) does.
+    pub fn fill(&self, out: &mut [f32], width: usize) {
+        assert!(self.octaves > 0, "Fbm needs at least one octave");
+        assert!(width > 0 && out.len().is_multiple_of(width), "the map must fill whole rows");
+        for (k, v) in out.iter_mut().enumerate() {
+            *v = self.sample((k / width) as f32, (k % width) as f32);
+        }
+    }
+}
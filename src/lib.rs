@@ -0,0 +1,86 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+/// Items that need the standard library, which is everything but the noise [`kernel`].
+macro_rules! with_std {
+    ($($item:item)*) => { $(#[cfg(feature = "std")] $item)* };
+}
+
+// parts of the kernel only the map generator uses
+#[cfg_attr(not(feature = "std"), allow(dead_code))]
+mod kernel;
+
+pub use kernel::{Fade, Fbm, LatticeSlice, NoiseKind, NoiseStyle, WorleyMode};
+
+with_std! {
+    use ndarray::Array2;
+
+    mod color;
+    mod config;
+    mod derived;
+    mod erosion;
+    mod generate;
+    mod gradients;
+    mod input;
+    mod noise;
+    mod output;
+    mod par;
+    mod pipeline;
+    mod post;
+    mod resample;
+    mod stats;
+    mod sweep;
+    mod volume;
+    #[cfg(feature = "wasm")]
+    mod wasm;
+
+    pub use color::{ColorRamp, TERRAIN_PALETTE};
+    pub use config::{attenuation_from_hurst, hurst_from_attenuation, ConfigError, HeightmapConfig};
+    pub use derived::{
+        ambient_occlusion, blur_occlusion, classify, difference_image, flow_colors, flow_field, land_mask,
+        normal_map, normal_map_from_gradient, offset_colors, packed_map, slope_map, TERRAIN_BANDS,
+    };
+    pub use erosion::{hydraulic_erode, thermal_erode, HydraulicParams};
+    pub use gradients::{GradientGrids, GradientsError};
+    pub use input::read_png;
+    pub use noise::GradientSource;
+    pub use output::{
+        ascii_art, sidecar_path, write_exr, write_gray_png, write_indexed_png, write_mask_png, write_obj,
+        write_png, write_png_alpha, write_png_strips, write_r16, write_raw, write_rgb_png, write_rgba_png,
+        write_s16, write_scale_json, BitDepth, ByteOrder, ImageEncoding, OutputFormat, OutputTarget, WorldScale,
+    };
+    pub use pipeline::{Craters, MapFn, Pipeline, Stage};
+    pub use post::{blend, blur, stitch, Curve, Invert, Normalization, StitchError};
+    pub use resample::{downsample, mip_chain, resize, sample_bilinear};
+    pub use stats::HeightmapStats;
+    pub use sweep::{contact_sheet, SweepAxis, SweepParam};
+
+    /// Generates a heightmap with the default squircle blend; see [`HeightmapConfig::generate`].
+    pub fn generate_heightmap(width: usize, height: usize, octaves: usize, attenuation: f32, seed: (u64, u64))
+        -> Array2<f32> {
+        HeightmapConfig::default()
+            .with_size(width, height)
+            .with_octaves(octaves)
+            .with_attenuation(attenuation)
+            .with_seed(seed)
+            .generate()
+    }
+
+    /// The map [`generate_heightmap`] generates, encoded in memory as [`write_png`] would write
+    /// it, without touching the filesystem; the `wasm` feature exports this to JavaScript.
+    ///
+    /// Fails with the [`ConfigError`] [`HeightmapConfig::validate`] finds, rather than panicking
+    /// as [`generate_heightmap`] would, since the arguments may come straight from a web page.
+    pub fn generate_png_bytes(width: usize, height: usize, octaves: usize, attenuation: f32, seed: (u64, u64))
+        -> Result<Vec<u8>, ConfigError> {
+        let config = HeightmapConfig::default()
+            .with_size(width, height)
+            .with_octaves(octaves)
+            .with_attenuation(attenuation)
+            .with_seed(seed);
+        config.validate()?;
+        let mut buf = Vec::new();
+        write_png(&mut buf, &config.generate(), BitDepth::Eight, config.clamp_range)
+            .expect("encoding into memory doesn't fail");
+        Ok(buf)
+    }
+}
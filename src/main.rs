@@ -1,163 +1,882 @@
-use std::{error::Error, fs::File};
+use std::{
+    env, error::Error, fs, io::{self, BufWriter, IsTerminal, Write}, path::{Path, PathBuf}, process, str::FromStr,
+};
 
-use image::{codecs::png::PngEncoder, ImageEncoder};
+use heightmap_gen::{
+    ascii_art, contact_sheet, difference_image, mip_chain, offset_colors, read_png, write_gray_png,
+    write_png_strips, write_rgb_png, BitDepth, ByteOrder, ColorRamp, Craters, Curve, Fade, GradientGrids,
+    GradientSource, HeightmapConfig, HeightmapStats, HydraulicParams, ImageEncoding, Invert, NoiseKind, NoiseStyle,
+    Normalization, OutputFormat, OutputTarget, Pipeline, Stage, SweepAxis, SweepParam, WorldScale, WorleyMode,
+    TERRAIN_BANDS, TERRAIN_PALETTE,
+};
 use ndarray::Array2;
-use pcg_mwc::Mwc256XXA64;
-use rand::Rng;
-
-trait CommonNumExt {
-    fn div_ceil(self, b: Self) -> Self;
-    fn div_floor(self, b: Self) -> Self;
-    fn gcd(self, b: Self) -> Self;
-    fn highest_one(self) -> Self;
-    fn lowest_one(self) -> Self;
-    fn sig_bits(self) -> u32;
+
+/// Pixels the occlusion of packed images is estimated over, unless the mode gives a radius.
+const PACKED_RADIUS: f32 = 8.;
+
+const USAGE: &str = "\
+usage: heightmap-gen [options]
+       heightmap-gen batch --count <n> --out <dir> [options]
+       heightmap-gen sweep --columns <param> <from> <to> <n> --rows <param> <from> <to> <n>
+                     [--thumb <size>] [options]
+       heightmap-gen diff <a.png> <b.png> <out.png> [--abs] [--gain <f>]
+
+batch writes <dir>/00000.png and so on, one map for each of the seeds 0 to n - 1 (with the
+second seed word kept), in the format chosen by --format, --depth and --colormap.
+
+sweep writes a contact sheet to --output: previews --thumb pixels along the longer side
+(default 128) with one parameter stepped evenly across the columns and another down the rows,
+labelled with their values and coloured with --colormap, or grayscale. The parameters are
+octaves, attenuation, lacunarity, perlin-weight, warp or gain.
+
+diff reads two grayscale heightmaps of the same size and writes how b differs from a: mid-gray
+where they agree and brighter where b is higher, or with --abs just how much they differ, black
+where they agree. --gain multiplies the differences (default 1); 8-bit maps from this program
+only use the darkest fifth of the samples, so small changes need a gain of 10 or more.
+
+options:
+  --config <path>          read settings from a TOML file, or JSON for a .json extension,
+                           with the other options overriding them; see the
+                           HeightmapConfig docs for the fields
+  --width <n>              map width in pixels (default 512)
+  --height <n>             map height in pixels (default 512)
+  --octaves <n>            number of noise octaves (default 8)
+  --finest-level <n>       skip octave levels below n to save memory (default 0)
+  --coarsest-level <n>     skip octave levels above n, keeping the others' amplitudes
+  --detail-cap <pixels>    skip octave levels with cells narrower than this, which speeds
+                           up large maps
+  --attenuation <f>        amplitude lost per octave, in 0..=1 (default 0.667)
+  --hurst <h>              set the attenuation from a Hurst exponent at the chosen
+                           lacunarity (default about 1.58)
+  --weights <f>,<f>,...    amplitude of each octave, coarsest first, instead of attenuation
+  --lacunarity <f>         cell size ratio between successive octaves, above 1 (default 2)
+  --perlin-weight <f>      weight of the noise against the squircle falloff (default 0.5)
+  --feature-scale <x> <y>  stretch of features along each axis (default 1 1)
+  --anisotropy <deg> <f>   stretch features by a factor of f along the direction this many
+                           degrees clockwise from the x axis
+  --rotate-octaves         turn each octave by its own angle from the seed, so lattice
+                           artifacts don't line up; not with wrapping
+  --noise <kind>           perlin, simplex, worley-f1, worley-f2, worley-f2-f1 or value
+                           (default perlin)
+  --gradients <source>     angles, table or hashed, how lattice gradients are picked
+                           (default hashed)
+  --angle-table            look gradient directions up in a table of 4096 angles, which is
+                           slightly faster
+  --octave-seeds           draw angles and table gradients for each octave from a seed of
+                           its own, so changing the octave count leaves the rest alone
+  --style <style>          fbm, ridged, ridged-squared or billow (default fbm)
+  --fade <curve>           linear, cubic or quintic interpolation for perlin noise
+                           (default cubic)
+  --normalize <mode>       scale-sum, min-max, none or equalize (default scale-sum)
+  --island <exponent>      sink the map towards its edges with a radial falloff
+  --bump <height> <power>  raise a mountain of the given height in the middle of the map,
+                           narrower with larger powers, before normalization
+  --bump-at <x> <y>        move the bump's peak to fractions x and y of the width and height
+  --edge-fade <n> <h>      fade heights towards h within n pixels of the edges the map
+                           doesn't wrap around at
+  --terraces <n> <f>       snap heights to n levels, blending back by smoothness f
+  --warp <pixels>          strength of domain warping (default 0)
+  --thermal <n> <talus>    run n passes of thermal erosion with the given talus height
+  --hydraulic <droplets>   run hydraulic erosion with this many droplets
+  --gain <f> <bias>        scale heights by f, then add bias (default 1 0)
+  --invert [<when>]        flip the map upside down, after-island (the default) for the
+                           finished map or before-island to keep the island falloff
+  --gamma <g>              raise heights within the clamp range to the power g, so above 1
+                           lowlands broaden under sharper peaks (default 1)
+  --curve <t:u,...>        remap heights through control points, each t and u running from
+                           0 at the low end of the clamp range to 1 at the high end
+  --grain <f>              amplitude of white noise added to each pixel (default 0)
+  --clamp <lo> <hi>        heights mapped onto the lowest and highest png and r16 samples,
+                           clamping the rest (default -1 1)
+  --tileable               make the noise wrap around at the edges
+  --wrap-x                 make the noise wrap around horizontally only
+  --wrap-y                 make the noise wrap around vertically only
+  --base <png> <f>         add the octaves to a grayscale png of heights, at its size, as
+                           detail of strength f, instead of generating a map from scratch
+  --disk <fill>            generate a round map in the middle of the output, with no seam
+                           around it, setting the pixels outside it to this height
+  --region <png> <fill>    only generate terrain where a black and white png of the map's
+                           size is white, setting the other pixels to this height; the
+                           size defaults to the png's
+  --craters <cell> <depth> <sharpness>
+                           the cratered preset: carve one crater per Worley cell of this
+                           size, this deep, with walls this steep (2 for round bowls)
+  --flatten-below <h> <band>
+                           raise everything below h to it, for plains and sea floors,
+                           rounding the edge off over a band of heights (0 for a sharp one)
+  --flatten-above <h> <band>
+                           lower everything above h to it, for plateaus and mesas
+  --origin <x> <y>         offset of the map in a larger world, with hashed gradients
+  --seed <u64> [<u64>]     seed words, decimal or 0x-prefixed hex
+  --random                 seed from system entropy and print the seed
+  --save-gradients <path>  also write the octave and warp gradient grids to a file
+  --load-gradients <path>  take the gradient grids from a file --save-gradients wrote
+                           instead of the seed, for the same map on any platform
+  --format <fmt>           png, raw little-endian f32, r16 16-bit terrain heights, s16
+                           signed 16-bit heights with 0 at 0 and the clamp range end
+                           furthest from it at ±32767, exr 32-bit float heights, or
+                           exr-clamped to clamp them first (default png)
+  --depth <bits>           png sample depth, 8 or 16 (default 8)
+  --little-endian          write r16 and s16 samples little-endian, for Unreal, instead of
+                           the big-endian Unity expects
+  --colormap <name>        write a terrain or grayscale colour preview instead of heights
+  --alpha <sea-level>      add an alpha channel to png height and colour images, with
+                           everything below sea level transparent
+  --scale <m> <m>          metres per pixel and per unit of height, written into png
+                           heights as text chunks and exr images as attributes, and to
+                           <output>.json for other height formats; colour, normal and
+                           other derived images don't get it
+  --sidecar                write <output>.json for every height output, even ones holding
+                           --scale
+  --output <path>          output file, or - for standard output (default output.png);
+                           images are written as jpeg, bmp or tiff for a .jpg, .bmp or
+                           .tiff extension, and png otherwise
+  --normals <path> <f>     also write a normal map with the given strength
+  --target <mode> <path>   also write another file; mode is png, png16, raw, terrain,
+                           grayscale, slope, flow, r16, r16le, s16, s16le, exr,
+                           exr-clamped, normals:<strength>, a mask:<sea-level> land mask, an
+                           obj:<height-scale> mesh, occlusion for ambient occlusion over
+                           8 directions out to 16 pixels or occlusion:<n>:<radius> to
+                           choose them, or packed with height, slope and occlusion in the
+                           red, green and blue channels, or packed:<radius> to estimate
+                           occlusion over that many pixels rather than 8, or classes for
+                           an indexed png of deep water, shallows, beach, grass, forest,
+                           rock and snow by height (repeatable)
+  --dump-octaves <mode>    also write each octave as <output>_octave_<k>.png, coarsest
+                           first; mode is raw, or scaled to multiply by the amplitude
+  --warp-map <path>        also write the offset --warp moves each pixel by as a png, x in
+                           red and y in green, with offsets of the full strength either
+                           way at the ends
+  --mipmaps                also write each output halved again and again down to one
+                           pixel, as <output>_mip_<k>, each level the 2 × 2 average of the
+                           one before
+  --ascii [<width>]        print a text preview this many characters wide (default 64)
+  --stream <rows>          generate and write the png output this many rows at a time, for
+                           maps too large for memory; needs scale-sum or no normalization,
+                           no erosion, no alpha, and no other outputs or previews
+  --preview [<size>]       generate the same terrain scaled down to this many pixels along
+                           the longer side (default 128)
+  --stats                  print the min, max, mean and standard deviation of the heights,
+                           and how many will be clamped at each end
+  --help                   print this message";
+
+struct Args {
+    config: HeightmapConfig,
+    /// Files to write, all rendered from the same generated map.
+    outputs: Vec<OutputTarget>,
+    stats: bool,
+    /// Whether to also write a mip chain of every output.
+    mipmaps: bool,
+    /// Width of a text preview to print, if any.
+    ascii: Option<usize>,
+    /// Whether to write each octave separately, and if so whether scaled by its amplitude.
+    dump_octaves: Option<bool>,
+    /// Where to write the warp offsets, if anywhere.
+    warp_map: Option<PathBuf>,
+    /// Rows per strip when streaming the output rather than generating it whole.
+    stream: Option<usize>,
+    /// Number of maps and directory to write them to, in batch mode.
+    batch: Option<(u64, PathBuf)>,
+    /// PNG to add the octaves to as detail, and their strength.
+    base: Option<(PathBuf, f32)>,
+    /// Files to write the gradient grids to and read them from.
+    save_gradients: Option<PathBuf>,
+    load_gradients: Option<PathBuf>,
+    /// Height outside the disk, when generating a round map.
+    disk: Option<f32>,
+    /// Parameter steps across the columns and down the rows of a contact sheet, and the size of
+    /// its thumbnails, in sweep mode.
+    sweep: Option<(SweepAxis, SweepAxis, usize)>,
+    /// Pipeline stages to run on the map after it is generated, such as the cratered preset.
+    stages: Vec<Stage>,
 }
 
-macro_rules! impl_common_num_ext {
-    ($($ix:tt = $ux:tt),*) => {
-        $(
-            impl CommonNumExt for $ux {
-                fn div_ceil(self, b: Self) -> Self {
-                    let q = self / b; let r = self % b;
-                    if r != 0 { q + 1 } else { q }
+fn parse_u64(s: &str) -> Result<u64, String> {
+    let parsed = match s.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => s.parse(),
+    };
+    parsed.map_err(|e| format!("invalid seed {s:?}: {e}"))
+}
+
+fn value<T: FromStr>(args: &mut impl Iterator<Item = String>, flag: &str) -> Result<T, String>
+where T::Err: std::fmt::Display {
+    let s = args.next().ok_or_else(|| format!("{flag} needs a value"))?;
+    s.parse().map_err(|e| format!("invalid value {s:?} for {flag}: {e}"))
+}
+
+fn parse_mode(s: &str) -> Result<OutputFormat, String> {
+    Ok(match s {
+        "png" => OutputFormat::Png(BitDepth::Eight),
+        "png16" => OutputFormat::Png(BitDepth::Sixteen),
+        "raw" => OutputFormat::Raw,
+        "r16" => OutputFormat::R16(ByteOrder::Big),
+        "exr" => OutputFormat::Exr { clamped: false },
+        "exr-clamped" => OutputFormat::Exr { clamped: true },
+        "r16le" => OutputFormat::R16(ByteOrder::Little),
+        "s16" => OutputFormat::S16(ByteOrder::Big),
+        "s16le" => OutputFormat::S16(ByteOrder::Little),
+        "terrain" => OutputFormat::Color(ColorRamp::terrain()),
+        "grayscale" => OutputFormat::Color(ColorRamp::grayscale()),
+        "slope" => OutputFormat::Slope,
+        "flow" => OutputFormat::Flow,
+        "occlusion" => OutputFormat::Occlusion { samples: 8, radius: 16. },
+        "packed" => OutputFormat::Packed(PACKED_RADIUS),
+        "classes" => OutputFormat::Classes { bands: TERRAIN_BANDS.to_vec(), palette: TERRAIN_PALETTE.to_vec() },
+        _ => if let Some(strength) = s.strip_prefix("normals:") {
+            OutputFormat::Normals(strength.parse().map_err(|e| format!("invalid normal strength {strength:?}: {e}"))?)
+        } else if let Some(params) = s.strip_prefix("occlusion:") {
+            let (samples, radius) =
+                params.split_once(':').ok_or_else(|| format!("expected occlusion:<n>:<radius>, got {s:?}"))?;
+            let count: usize = samples.parse().map_err(|e| format!("invalid direction count {samples:?}: {e}"))?;
+            if count == 0 {
+                return Err(format!("invalid direction count {samples:?}: occlusion needs at least one direction"));
+            }
+            let pixels: f32 = radius.parse().map_err(|e| format!("invalid occlusion radius {radius:?}: {e}"))?;
+            if !(pixels > 0. && pixels.is_finite()) {
+                return Err(format!("invalid occlusion radius {radius:?}: it must be positive and finite"));
+            }
+            OutputFormat::Occlusion { samples: count, radius: pixels }
+        } else if let Some(radius) = s.strip_prefix("packed:") {
+            OutputFormat::Packed(radius.parse().map_err(|e| format!("invalid occlusion radius {radius:?}: {e}"))?)
+        } else if let Some(sea_level) = s.strip_prefix("mask:") {
+            OutputFormat::Mask(sea_level.parse().map_err(|e| format!("invalid sea level {sea_level:?}: {e}"))?)
+        } else if let Some(z_scale) = s.strip_prefix("obj:") {
+            OutputFormat::Obj(z_scale.parse().map_err(|e| format!("invalid height scale {z_scale:?}: {e}"))?)
+        } else {
+            return Err(format!("unknown output mode {s:?}"));
+        },
+    })
+}
+
+/// A sweep axis from the four values after `--columns` or `--rows`.
+fn sweep_axis(args: &mut impl Iterator<Item = String>, flag: &str) -> Result<SweepAxis, String> {
+    let name: String = value(args, flag)?;
+    let param = SweepParam::ALL.into_iter().find(|p| p.name() == name)
+        .ok_or_else(|| format!("unknown sweep parameter {name:?}"))?;
+    let (from, to) = (value(args, flag)?, value(args, flag)?);
+    let steps = value(args, flag)?;
+    if steps == 0 {
+        return Err(format!("{flag} needs at least one step"));
+    }
+    Ok(SweepAxis::linspace(param, from, to, steps))
+}
+
+fn parse_curve(s: &str) -> Result<Curve, String> {
+    let points = s.split(',').map(|point| {
+        let (t, u) = point.split_once(':').ok_or_else(|| format!("curve point {point:?} isn't t:u"))?;
+        Ok((t.parse().map_err(|e| format!("invalid curve point {point:?}: {e}"))?,
+            u.parse().map_err(|e| format!("invalid curve point {point:?}: {e}"))?))
+    });
+    Ok(Curve::Points(points.collect::<Result<_, String>>()?))
+}
+
+/// Reads a config from a JSON file if `path` ends in `.json`, and from TOML otherwise.
+#[cfg(feature = "config-file")]
+fn load_config(path: &Path) -> Result<HeightmapConfig, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("couldn't read {}: {e}", path.display()))?;
+    let parsed = if path.extension().is_some_and(|ext| ext == "json") {
+        serde_json::from_str(&text).map_err(|e| e.to_string())
+    } else {
+        toml::from_str(&text).map_err(|e| e.to_string())
+    };
+    parsed.map_err(|e| format!("invalid config file {}: {e}", path.display()))
+}
+
+/// The white pixels of a black and white png, or any pixels brighter than mid-gray.
+fn load_mask(path: &Path) -> Result<Array2<bool>, String> {
+    let error = |e: &dyn Error| format!("couldn't read {}: {e}", path.display());
+    let file = fs::File::open(path).map_err(|e| error(&e))?;
+    let heights = read_png(io::BufReader::new(file)).map_err(|e| error(&e))?;
+    Ok(heights.mapv(|v| v > 0.))
+}
+
+#[cfg(not(feature = "config-file"))]
+fn load_config(_: &Path) -> Result<HeightmapConfig, String> {
+    Err("--config needs the config-file feature".into())
+}
+
+fn parse_args() -> Result<Args, String> {
+    // a config file is read first wherever it is given, so the other options override it
+    let mut config = match env::args().skip_while(|a| a != "--config").nth(1) {
+        Some(path) => load_config(Path::new(&path))?,
+        None if env::args().any(|a| a == "--config") => return Err("--config needs a value".into()),
+        None => HeightmapConfig::default(),
+    };
+    let mut format = "png".to_string();
+    let mut depth = BitDepth::Eight;
+    let mut order = ByteOrder::Big;
+    let mut colormap = None;
+    let mut alpha = None;
+    let mut output = PathBuf::from("output.png");
+    let mut outputs = Vec::new();
+    let mut stats = false;
+    let mut hurst = None;
+    let mut dump_octaves = None;
+    let mut warp_map = None;
+    let mut mipmaps = false;
+    let mut stream = None;
+    let mut base = None;
+    let mut disk = None;
+    let mut stages = Vec::new();
+    let (mut save_gradients, mut load_gradients) = (None, None);
+    let mut preview = None;
+    let mut ascii = None;
+    let mut bump_at = None;
+    let (mut scale, mut sidecar) = (None, false);
+
+    let mut args = env::args().skip(1).peekable();
+    let batch_mode = args.next_if_eq("batch").is_some();
+    let sweep_mode = !batch_mode && args.next_if_eq("sweep").is_some();
+    let (mut count, mut dir) = (None, None);
+    let (mut columns, mut rows, mut thumb) = (None, None, 128);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--config" => {
+                args.next();
+            }
+            "--width" => config.width = value(&mut args, &arg)?,
+            "--height" => config.height = value(&mut args, &arg)?,
+            "--octaves" => config.octaves = value(&mut args, &arg)?,
+            "--finest-level" => config.finest_level = value(&mut args, &arg)?,
+            "--detail-cap" => config.detail_cap = Some(value(&mut args, &arg)?),
+            "--coarsest-level" => config.coarsest_level = Some(value(&mut args, &arg)?),
+            "--attenuation" => config.attenuation = value(&mut args, &arg)?,
+            "--hurst" => hurst = Some(value(&mut args, &arg)?),
+            "--weights" => {
+                let list = value::<String>(&mut args, &arg)?;
+                let weights = list.split(',')
+                    .map(|w| w.trim().parse().map_err(|e| format!("invalid weight {w:?}: {e}")));
+                config.octave_weights = Some(weights.collect::<Result<_, String>>()?);
+            }
+            "--lacunarity" => config.lacunarity = value(&mut args, &arg)?,
+            "--perlin-weight" => config.perlin_weight = value(&mut args, &arg)?,
+            "--feature-scale" => config.feature_scale = (value(&mut args, &arg)?, value(&mut args, &arg)?),
+            "--rotate-octaves" => config.octave_rotation = true,
+            "--anisotropy" => {
+                let angle: f32 = value(&mut args, &arg)?;
+                config.anisotropy = Some((angle.to_radians(), value(&mut args, &arg)?));
+            }
+            "--noise" => {
+                config.noise = match value::<String>(&mut args, &arg)?.as_str() {
+                    "perlin" => NoiseKind::Perlin,
+                    "simplex" => NoiseKind::Simplex,
+                    "worley-f1" => NoiseKind::Worley(WorleyMode::F1),
+                    "worley-f2" => NoiseKind::Worley(WorleyMode::F2),
+                    "worley-f2-f1" => NoiseKind::Worley(WorleyMode::F2MinusF1),
+                    "value" => NoiseKind::Value,
+                    s => return Err(format!("unknown noise kind {s:?}")),
                 }
-                fn div_floor(self, b: Self) -> Self { self / b }
-                fn gcd(self, mut b: Self) -> Self {
-                    let mut a = self;
-                    if a == 0 || b == 0 { return a | b; }
-                    let shift = (a | b).trailing_zeros();
-                    a >>= a.trailing_zeros();
-                    b >>= b.trailing_zeros();
-                    while a != b {
-                        if a > b { a -= b; a >>= a.trailing_zeros(); }
-                        else { b -= a; b >>= b.trailing_zeros(); }
-                    }
-                    a << shift
+            }
+            "--gradients" => {
+                config.gradients = match value::<String>(&mut args, &arg)?.as_str() {
+                    "angles" => GradientSource::RandomAngles,
+                    "table" => GradientSource::Table,
+                    "hashed" => GradientSource::Hashed,
+                    s => return Err(format!("unknown gradient source {s:?}")),
                 }
-                #[inline] fn highest_one(self) -> Self { 
-                    if self == 0 { 0 } else { const ONE: $ux = 1; ONE << self.sig_bits() - 1 } 
+            }
+            "--angle-table" => config.angle_table = true,
+            "--octave-seeds" => config.octave_seeds = true,
+            "--style" => {
+                config.style = match value::<String>(&mut args, &arg)?.as_str() {
+                    "fbm" => NoiseStyle::Fbm,
+                    "ridged" => NoiseStyle::Ridged { squared: false },
+                    "ridged-squared" => NoiseStyle::Ridged { squared: true },
+                    "billow" => NoiseStyle::Billow,
+                    s => return Err(format!("unknown noise style {s:?}")),
                 }
-                #[inline] fn lowest_one(self) -> Self { self & self.wrapping_neg() }
-                #[inline] fn sig_bits(self) -> u32 { std::mem::size_of::<$ux>() as u32 * 8 - self.leading_zeros() }
             }
-
-            impl CommonNumExt for $ix {
-                fn div_ceil(self, b: Self) -> Self {
-                    let q = self / b; let r = self % b;
-                    if self ^ b >= 0 && r != 0 { q + 1 } else { q }
+            "--fade" => {
+                config.fade = match value::<String>(&mut args, &arg)?.as_str() {
+                    "linear" => Fade::Linear,
+                    "cubic" => Fade::Cubic,
+                    "quintic" => Fade::Quintic,
+                    s => return Err(format!("unknown fade {s:?}")),
+                }
+            }
+            "--normalize" => {
+                config.normalization = match value::<String>(&mut args, &arg)?.as_str() {
+                    "scale-sum" => Normalization::ScaleSum,
+                    "min-max" => Normalization::MinMax,
+                    "none" => Normalization::None,
+                    "equalize" => Normalization::Equalize,
+                    s => return Err(format!("unknown normalization {s:?}")),
+                }
+            }
+            "--island" => config.island = Some(value(&mut args, &arg)?),
+            "--bump" => {
+                let (amplitude, power) = (value(&mut args, &arg)?, value(&mut args, &arg)?);
+                config.bump = Some((amplitude, power, config.bump.map_or((0.5, 0.5), |(.., centre)| centre)));
+            }
+            "--bump-at" => bump_at = Some((value(&mut args, &arg)?, value(&mut args, &arg)?)),
+            "--edge-fade" => config.edge_fade = Some((value(&mut args, &arg)?, value(&mut args, &arg)?)),
+            "--terraces" => config.terraces = Some((value(&mut args, &arg)?, value(&mut args, &arg)?)),
+            "--warp" => config.warp_strength = value(&mut args, &arg)?,
+            "--thermal" => config.thermal_erosion = Some((value(&mut args, &arg)?, value(&mut args, &arg)?)),
+            "--hydraulic" => config.hydraulic_erosion = Some((value(&mut args, &arg)?, HydraulicParams::default())),
+            "--gain" => (config.gain, config.bias) = (value(&mut args, &arg)?, value(&mut args, &arg)?),
+            "--invert" => {
+                // when is optional
+                config.invert = Some(match args.next_if(|a| !a.starts_with("--")).as_deref() {
+                    Some("after-island") | None => Invert::AfterIsland,
+                    Some("before-island") => Invert::BeforeIsland,
+                    Some(s) => {
+                        return Err(format!("unknown inversion {s:?}, expected before-island or after-island"))
+                    }
+                });
+            }
+            "--gamma" => config.curve = Some(Curve::Gamma(value(&mut args, &arg)?)),
+            "--curve" => config.curve = Some(parse_curve(&args.next().ok_or("--curve needs a value")?)?),
+            "--grain" => config.grain = value(&mut args, &arg)?,
+            "--clamp" => config.clamp_range = (value(&mut args, &arg)?, value(&mut args, &arg)?),
+            "--tileable" => config = config.with_tileable(true),
+            "--wrap-x" => config.wrap_x = true,
+            "--wrap-y" => config.wrap_y = true,
+            "--origin" => config.origin = (value(&mut args, &arg)?, value(&mut args, &arg)?),
+            "--save-gradients" => save_gradients = Some(value(&mut args, &arg)?),
+            "--load-gradients" => load_gradients = Some(value(&mut args, &arg)?),
+            "--seed" => {
+                let first = args.next().ok_or("--seed needs a value")?;
+                config.seed.0 = parse_u64(&first)?;
+                // an optional second word; otherwise the default one is kept
+                if let Some(second) = args.next_if(|a| !a.starts_with("--")) {
+                    config.seed.1 = parse_u64(&second)?;
+                }
+            }
+            "--random" => {
+                config.seed = rand::random();
+                eprintln!("seed: {:#x} {:#x}", config.seed.0, config.seed.1);
+            }
+            "--format" => format = value(&mut args, &arg)?,
+            "--depth" => {
+                depth = match value::<u32>(&mut args, &arg)? {
+                    8 => BitDepth::Eight,
+                    16 => BitDepth::Sixteen,
+                    d => return Err(format!("unsupported bit depth {d}, expected 8 or 16")),
                 }
-                fn div_floor(self, b: Self) -> Self { 
-                    let q = self / b; let r = self % b;
-                    if self ^ b < 0 && r != 0 { q - 1 } else { q }
+            }
+            "--little-endian" => order = ByteOrder::Little,
+            "--alpha" => alpha = Some(value(&mut args, &arg)?),
+            "--scale" => {
+                let (per_pixel, per_unit): (f32, f32) = (value(&mut args, &arg)?, value(&mut args, &arg)?);
+                if !(per_pixel > 0. && per_unit > 0. && per_pixel.is_finite() && per_unit.is_finite()) {
+                    return Err(format!("--scale needs positive scales, got {per_pixel} {per_unit}"));
                 }
-                fn gcd(self, b: Self) -> Self {
-                    fn w_abs(x: $ix) -> $ux { (if x.is_negative() { x.wrapping_neg() } else { x }) as _ }
-                    w_abs(self).gcd(w_abs(b)) as _
+                scale = Some(WorldScale::new(per_pixel, per_unit));
+            }
+            "--sidecar" => sidecar = true,
+            "--colormap" => {
+                colormap = Some(match value::<String>(&mut args, &arg)?.as_str() {
+                    "terrain" => ColorRamp::terrain(),
+                    "grayscale" => ColorRamp::grayscale(),
+                    s => return Err(format!("unknown colour map {s:?}")),
+                })
+            }
+            "--output" => output = value(&mut args, &arg)?,
+            "--normals" => {
+                let path: PathBuf = value(&mut args, &arg)?;
+                outputs.push(OutputTarget::new(OutputFormat::Normals(value(&mut args, &arg)?), path));
+            }
+            "--target" => {
+                let mode = parse_mode(&value::<String>(&mut args, &arg)?)?;
+                outputs.push(OutputTarget::new(mode, value::<PathBuf>(&mut args, &arg)?));
+            }
+            "--stream" => stream = Some(value(&mut args, &arg)?),
+            "--preview" => {
+                // the size is optional
+                preview = Some(match args.next_if(|a| !a.starts_with("--")) {
+                    Some(s) => s.parse().map_err(|e| format!("invalid preview size {s:?}: {e}"))?,
+                    None => 128,
+                });
+            }
+            "--base" => base = Some((value(&mut args, &arg)?, value(&mut args, &arg)?)),
+            "--disk" => disk = Some(value(&mut args, &arg)?),
+            "--region" => {
+                let (path, fill): (PathBuf, f32) = (value(&mut args, &arg)?, value(&mut args, &arg)?);
+                let mask = load_mask(&path)?;
+                config = config.with_size(mask.ncols(), mask.nrows()).with_region_mask(Some(mask), fill);
+            }
+            "--craters" => {
+                let (cell_size, depth, sharpness): (f32, f32, f32) =
+                    (value(&mut args, &arg)?, value(&mut args, &arg)?, value(&mut args, &arg)?);
+                if !(cell_size > 0. && cell_size.is_finite() && depth.is_finite() && sharpness.is_finite()) {
+                    return Err(format!("--craters needs a positive cell size and a finite depth and sharpness, \
+                        got {cell_size} {depth} {sharpness}"));
                 }
-                #[inline] fn highest_one(self) -> Self { (self as $ux).highest_one() as _ }
-                #[inline] fn lowest_one(self) -> Self { self & self.wrapping_neg() }
-                #[inline] fn sig_bits(self) -> u32 { std::mem::size_of::<$ix>() as u32 * 8 - self.leading_zeros() }
+                stages.push(Stage::Craters(Craters { cell_size, depth, sharpness, ..Craters::default() }));
+            }
+            "--flatten-below" => {
+                let (level, band) = (value(&mut args, &arg)?, value(&mut args, &arg)?);
+                stages.push(Stage::Flatten { below: Some(level), above: None, band });
+            }
+            "--flatten-above" => {
+                let (level, band) = (value(&mut args, &arg)?, value(&mut args, &arg)?);
+                stages.push(Stage::Flatten { below: None, above: Some(level), band });
+            }
+            "--mipmaps" => mipmaps = true,
+            "--stats" => stats = true,
+            "--ascii" => {
+                // the width is optional
+                ascii = Some(match args.next_if(|a| !a.starts_with("--")) {
+                    Some(w) => w.parse().map_err(|e| format!("invalid ascii width {w:?}: {e}"))?,
+                    None => 64,
+                });
+            }
+            "--warp-map" => warp_map = Some(value(&mut args, &arg)?),
+            "--dump-octaves" => {
+                dump_octaves = Some(match value::<String>(&mut args, &arg)?.as_str() {
+                    "raw" => false,
+                    "scaled" => true,
+                    s => return Err(format!("unknown octave dump mode {s:?}, expected raw or scaled")),
+                })
+            }
+            "--count" if batch_mode => count = Some(value(&mut args, &arg)?),
+            "--out" if batch_mode => dir = Some(value(&mut args, &arg)?),
+            "--columns" if sweep_mode => columns = Some(sweep_axis(&mut args, &arg)?),
+            "--rows" if sweep_mode => rows = Some(sweep_axis(&mut args, &arg)?),
+            "--thumb" if sweep_mode => thumb = value(&mut args, &arg)?,
+            "--help" => {
+                println!("{USAGE}");
+                process::exit(0);
             }
-        )*
+            _ => return Err(format!("unknown argument {arg:?}\n{USAGE}")),
+        }
     }
-}
-impl_common_num_ext!(i8 = u8, i16 = u16, i32 = u32, i64 = u64, i128 = u128, isize = usize);
 
-const HEIGHT: usize = 512;
-const WIDTH: usize = 512;
-const OCTAVES: usize = 8;
-const ATTENUATION: f32 = 2. / 3.;
+    if let Some(centre) = bump_at {
+        match &mut config.bump {
+            Some((.., at)) => *at = centre,
+            None => return Err("--bump-at needs --bump".into()),
+        }
+    }
+    // applied last, so it uses the final lacunarity
+    if let Some(h) = hurst {
+        config = config.with_hurst(h);
+    }
+    if let Some(size) = preview {
+        config = config.preview(size);
+    }
+    config.validate().map_err(|e| e.to_string())?;
 
-const PERLIN_WEIGHT: f32 = 1. / 2.;
+    let format = match format.as_str() {
+        "png" => match colormap {
+            Some(ramp) => OutputFormat::Color(ramp),
+            None => OutputFormat::Png(depth),
+        },
+        "raw" => OutputFormat::Raw,
+        "r16" => OutputFormat::R16(order),
+        "s16" => OutputFormat::S16(order),
+        "exr" => OutputFormat::Exr { clamped: false },
+        "exr-clamped" => OutputFormat::Exr { clamped: true },
+        s => return Err(format!("unknown format {s:?}, expected png, raw, r16, s16, exr or exr-clamped")),
+    };
+    outputs.insert(0, OutputTarget::new(format, output));
+    for target in &mut outputs {
+        target.range = config.clamp_range;
+        target.wrap = if disk.is_some() { (false, false) } else { (config.wrap_x, config.wrap_y) };
+        target.alpha = alpha;
+        if target.writes_heights() {
+            target.scale = scale;
+            target.sidecar = sidecar;
+        }
+    }
+    if sidecar && scale.is_none() {
+        return Err("--sidecar needs --scale".into());
+    }
 
-type Point = (f32, f32);
+    let batch = match (batch_mode, count, dir) {
+        (false, ..) => None,
+        (true, Some(count), Some(dir)) => Some((count, dir)),
+        (true, ..) => return Err("batch needs --count and --out".into()),
+    };
+    let sweep = match (sweep_mode, columns, rows) {
+        (false, ..) => None,
+        (true, Some(columns), Some(rows)) => Some((columns, rows, thumb)),
+        (true, ..) => return Err("sweep needs --columns and --rows".into()),
+    };
+    if sweep.is_some() {
+        if thumb == 0 {
+            return Err("--thumb needs at least one pixel".into());
+        }
+        let others = outputs.len() > 1 || mipmaps || stats || ascii.is_some() || dump_octaves.is_some();
+        let others = others || warp_map.is_some() || stream.is_some() || !stages.is_empty();
+        let sources = base.is_some() || disk.is_some() || save_gradients.is_some() || load_gradients.is_some();
+        if others || sources || !matches!(outputs[0].format, OutputFormat::Png(_) | OutputFormat::Color(_)) {
+            return Err("sweep only writes a single png contact sheet".into());
+        }
+    }
 
-fn dot_grid_gradient(grid: &Array2<Point>, ix: usize, iy: usize, x: f32, y: f32) -> f32 {
-    let gradient = grid[[ix, iy]];
+    if let Some(rows) = stream {
+        if rows == 0 {
+            return Err("--stream needs at least one row".into());
+        }
+        if !matches!(config.normalization, Normalization::ScaleSum | Normalization::None) {
+            return Err("--stream needs scale-sum or no normalization".into());
+        }
+        if config.thermal_erosion.is_some() || config.hydraulic_erosion.is_some() {
+            return Err("--stream can't be used with erosion".into());
+        }
+        let png = matches!(outputs[0].format, OutputFormat::Png(_))
+            && ImageEncoding::from_path(&outputs[0].path) == ImageEncoding::Png;
+        let others = outputs.len() > 1 || mipmaps || stats || ascii.is_some() || dump_octaves.is_some();
+        let others = others || warp_map.is_some() || batch.is_some();
+        if !png || alpha.is_some() || others {
+            return Err("--stream only writes a single png of heights".into());
+        }
+    }
 
-    let dx = x - ix as f32;
-    let dy = y - iy as f32;
+    if base.is_some() && (stream.is_some() || batch.is_some()) {
+        return Err("--base can't be used with --stream or batch".into());
+    }
+    if disk.is_some() && (base.is_some() || stream.is_some() || batch.is_some()) {
+        return Err("--disk can't be used with --base, --stream or batch".into());
+    }
 
-    dx * gradient.0 + dy * gradient.1
-}
+    let other_source = base.is_some() || disk.is_some() || stream.is_some() || load_gradients.is_some();
+    if !stages.is_empty() && (other_source || batch.is_some()) {
+        return Err("--craters and flattening can't be used with --base, --disk, --stream, --load-gradients \
+            or batch".into());
+    }
+    let whole_map = base.is_none() && disk.is_none() && stream.is_none() && batch.is_none();
+    if (save_gradients.is_some() || load_gradients.is_some()) && !whole_map {
+        return Err("gradient files can't be used with --base, --disk, --stream or batch".into());
+    }
+    if warp_map.is_some() {
+        if config.warp_strength == 0. {
+            return Err("--warp-map needs --warp".into());
+        }
+        if !whole_map || load_gradients.is_some() {
+            return Err("--warp-map can't be used with --base, --disk, --stream, --load-gradients or batch".into());
+        }
+    }
+    if batch.is_some() {
+        if mipmaps {
+            return Err("--mipmaps can't be used with batch".into());
+        }
+        if outputs.len() > 1 || stats || ascii.is_some() || dump_octaves.is_some() {
+            return Err("batch only writes one file per seed, so --target, --normals, --stats, --ascii and \
+                --dump-octaves can't be used with it".into());
+        }
+    }
+    if mipmaps && outputs.iter().any(|target| target.path == Path::new("-")) {
+        return Err("--mipmaps can't write to standard output".into());
+    }
+    if ascii.is_some() && outputs.iter().any(|target| target.path == Path::new("-")) {
+        return Err("--ascii can't be used when writing to standard output".into());
+    }
+    if dump_octaves.is_some() && outputs[0].path == Path::new("-") {
+        return Err("--dump-octaves names its files after --output, so it can't write to standard output".into());
+    }
 
-fn interpolate(a0: f32, a1: f32, w: f32) -> f32 {
-    (a1 - a0) * (3. - w * 2.) * w * w + a0
+    Ok(Args { config, outputs, mipmaps, stats, ascii, dump_octaves, warp_map, stream, batch, sweep, base, disk,
+        stages, save_gradients, load_gradients })
 }
 
-fn interpolate_linear(a0: f32, a1: f32, w: f32) -> f32 {
-    (a1 - a0) * w + a0
+/// File extension for maps written in `format`.
+fn extension(format: &OutputFormat) -> &'static str {
+    match format {
+        OutputFormat::Raw => "raw",
+        OutputFormat::R16(_) => "r16",
+        OutputFormat::S16(_) => "s16",
+        OutputFormat::Exr { .. } => "exr",
+        OutputFormat::Obj(_) => "obj",
+        _ => "png",
+    }
 }
 
-fn perlin(src: &Array2<Point>, x: f32, y: f32) -> f32 {
-    let x0 = x as usize;
-    let y0 = y as usize;
-    let x1 = x0 + 1;
-    let y1 = y0 + 1;
+/// Writes `count` maps from `config` with sequential seeds into `dir` as `target` would, in
+/// parallel with the `parallel` feature.
+fn batch(config: &HeightmapConfig, target: &OutputTarget, count: u64, dir: &Path)
+    -> Result<(), Box<dyn Error>> {
+    fs::create_dir_all(dir)?;
+
+    let write = |k: u64| {
+        let config = config.clone().with_seed((k, config.seed.1));
+        let path = dir.join(format!("{k:05}.{}", extension(&target.format)));
+        OutputTarget { path, ..target.clone() }.write(&config.generate())
+    };
+
+    #[cfg(feature = "parallel")]
+    {
+        use ndarray::parallel::prelude::*;
+        (0..count).into_par_iter().try_for_each(write)?;
+    }
+    #[cfg(not(feature = "parallel"))]
+    (0..count).try_for_each(write)?;
 
-    let sx = x - x0 as f32;
-    let sy = y - y0 as f32;
+    eprintln!("wrote {count} maps to {}", dir.display());
+    Ok(())
+}
 
-    let n0 = dot_grid_gradient(&src, x0, y0, x, y);
-    let n1 = dot_grid_gradient(&src, x1, y0, x, y);
-    let ix0 = interpolate(n0, n1, sx);
+/// Reads `a` and `b` and writes their [`difference_image`] to `out`, from the arguments after
+/// `diff`.
+fn diff(mut args: impl Iterator<Item = String>) -> Result<(), Box<dyn Error>> {
+    let (mut paths, mut absolute, mut gain) = (Vec::new(), false, 1.);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--abs" => absolute = true,
+            "--gain" => gain = value(&mut args, &arg)?,
+            _ if arg.starts_with("--") => return Err(format!("unknown option {arg:?}").into()),
+            _ => paths.push(PathBuf::from(arg)),
+        }
+    }
+    let [a, b, out] = &paths[..] else { return Err("diff needs two maps to compare and an output".into()) };
 
-    let n0 = dot_grid_gradient(&src, x0, y1, x, y);
-    let n1 = dot_grid_gradient(&src, x1, y1, x, y);
-    let ix1 = interpolate(n0, n1, sx);
+    let read = |path: &Path| read_png(io::BufReader::new(fs::File::open(path)?));
+    let (a, b) = (read(a)?, read(b)?);
+    if a.dim() != b.dim() {
+        let (a, b) = (a.dim(), b.dim());
+        return Err(format!("can't compare a {} × {} map with a {} × {} one", a.1, a.0, b.1, b.0).into());
+    }
 
-    interpolate(ix0, ix1, sy)
+    let changed = a.iter().zip(&b).filter(|(a, b)| a != b).count();
+    let largest = a.iter().zip(&b).map(|(a, b)| (b - a).abs()).fold(0., f32::max);
+    println!("{changed} of {} pixels differ, by at most {largest:.4}", a.len());
+    write_gray_png(BufWriter::new(fs::File::create(out)?), &difference_image(&a, &b, absolute, gain))?;
+    Ok(())
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
-    let mut rng = Mwc256XXA64::new(0x243F6A8885A308D3, 0x13198A2E03707344);
-
-    let mut scale = 1f32;
-    let mut scale_sum = 0f32;
+    if env::args().nth(1).as_deref() == Some("diff") {
+        if let Err(e) = diff(env::args().skip(2)) {
+            eprintln!("{e}");
+            process::exit(2);
+        }
+        return Ok(());
+    }
 
-    let mut res = Array2::from_elem([HEIGHT, WIDTH], 0f32);
+    let mut args = parse_args().unwrap_or_else(|e| {
+        eprintln!("{e}");
+        process::exit(2);
+    });
 
-    for level in (0..OCTAVES).rev() {
-        let cell_size = 1 << level;
+    let degenerate = args.config.degenerate_octaves();
+    if !degenerate.is_empty() {
+        let levels: Vec<String> = degenerate.iter().map(|l| l.to_string()).collect();
+        eprintln!("warning: cells larger than the {} × {} map at octave levels {}; they add almost nothing",
+            args.config.width, args.config.height, levels.join(", "));
+    }
 
-        let h = HEIGHT.div_ceil(cell_size);
-        let w = WIDTH.div_ceil(cell_size);
+    if let Some((count, dir)) = &args.batch {
+        return batch(&args.config, &args.outputs[0], *count, dir);
+    }
+    if let Some((columns, rows, thumb)) = &args.sweep {
+        let ramp = match &args.outputs[0].format {
+            OutputFormat::Color(ramp) => ramp.clone(),
+            _ => ColorRamp::grayscale(),
+        };
+        let sheet = contact_sheet(&args.config, columns, rows, *thumb, &ramp).unwrap_or_else(|e| {
+            eprintln!("{e}");
+            process::exit(2);
+        });
+        let path = &args.outputs[0].path;
+        let file: Box<dyn Write> = if path == Path::new("-") {
+            Box::new(io::stdout().lock())
+        } else {
+            Box::new(fs::File::create(path)?)
+        };
+        let mut writer = BufWriter::new(file);
+        write_rgb_png(&mut writer, &sheet)?;
+        writer.flush()?;
+        eprintln!("wrote a {} × {} sweep to {}, {} across the columns and {} down the rows", columns.values.len(),
+            rows.values.len(), path.display(), columns.param.name(), rows.param.name());
+        return Ok(());
+    }
+    if let (Some(rows), OutputFormat::Png(depth)) = (args.stream, &args.outputs[0].format) {
+        let path = &args.outputs[0].path;
+        let file: Box<dyn Write> = if path == Path::new("-") {
+            Box::new(io::stdout().lock())
+        } else {
+            Box::new(fs::File::create(path)?)
+        };
+        let mut writer = BufWriter::new(file);
+        write_png_strips(&mut writer, &args.config, *depth, rows)?;
+        writer.flush()?;
+        // streamed pngs don't carry text chunks, so the scale always goes to the sidecar
+        let target = OutputTarget { sidecar: true, ..args.outputs[0].clone() };
+        return Ok(target.write_sidecar(args.config.width, args.config.height)?);
+    }
 
-        let mut vecs = Array2::from_elem([h+1, w+1], Point::default());
-        for i in 0..=h { for j in 0..=w {
-            vecs[[i, j]] = (rng.gen::<f32>() * std::f32::consts::TAU).sin_cos();
-        }}
+    let loaded = match &args.load_gradients {
+        Some(path) => Some(GradientGrids::read(io::BufReader::new(fs::File::open(path)?))?),
+        None => None,
+    };
+    let res = if let Some((path, strength)) = &args.base {
+        let base = read_png(io::BufReader::new(fs::File::open(path)?))?;
+        args.config = args.config.with_size(base.ncols(), base.nrows());
+        args.config.generate_detail(&base, *strength)
+    } else if let Some(fill) = args.disk {
+        args.config.generate_disk(fill)
+    } else if let Some(grids) = &loaded {
+        args.config.generate_from_grids(grids)?
+    } else if !args.stages.is_empty() {
+        let stages = std::mem::take(&mut args.stages).into_iter().map(|stage| (stage, true)).collect();
+        Pipeline { stages }.run(&args.config)?
+    } else if io::stderr().is_terminal() {
+        let res = args.config.generate_with_progress(|fraction| eprint!("\rgenerating {:3.0}%", fraction * 100.));
+        eprintln!();
+        res
+    } else {
+        args.config.generate()
+    };
 
-        for i in 0..HEIGHT { for j in 0..WIDTH {
-            let x = i as f32 / cell_size as f32;
-            let y = j as f32 / cell_size as f32;
-            res[[i, j]] += perlin(&vecs, x, y) * scale;
-        }}
+    if let Some(path) = &args.save_gradients {
+        let mut writer = BufWriter::new(fs::File::create(path)?);
+        loaded.unwrap_or_else(|| args.config.gradient_grids()).write(&mut writer)?;
+        writer.flush()?;
+    }
 
-        scale_sum += scale;
-        scale *= (1. - ATTENUATION);
+    let (lo, hi) = args.config.clamp_range;
+    let stats = HeightmapStats::with_range(&res, (lo, hi));
+    if args.stats {
+        eprintln!("{stats}");
+    }
+    let clipped = stats.clipped.0 + stats.clipped.1;
+    if (args.config.gain != 1. || args.config.bias != 0.) && clipped > 0 {
+        eprintln!("warning: {clipped} pixels ({:.1}%) are outside {lo}..{hi} after gain and bias and will be \
+            clamped", clipped as f32 / res.len() as f32 * 100.);
     }
 
-    for i in 0..HEIGHT { for j in 0..WIDTH {
-        res[[i, j]] /= scale_sum;
+    if let Some(width) = args.ascii {
+        print!("{}", ascii_art(&res, width));
+    }
 
-        // squircle adjustment
-        let d = |x: usize, w: usize| x as f32 / w as f32 * 2. - 1.;
-        let dis = (d(i, HEIGHT).powi(4) + d(j, WIDTH).powi(4)) / 2.;
-        let r = interpolate_linear(1., -2.5, dis);
+    if let Some(scaled) = args.dump_octaves {
+        let primary = &args.outputs[0].path;
+        let stem = primary.file_stem().unwrap_or_default().to_string_lossy();
+        for (k, layer) in args.config.generate_octaves(scaled).iter().enumerate() {
+            let path = primary.with_file_name(format!("{stem}_octave_{k}.png"));
+            OutputTarget::new(OutputFormat::Color(ColorRamp::grayscale()), path).write(layer)?;
+        }
+    }
 
-        res[[i, j]] = res[[i, j]] * PERLIN_WEIGHT + r * (1. - PERLIN_WEIGHT)
-    }}
+    if let Some(path) = &args.warp_map {
+        let colors = offset_colors(&args.config.warp_offsets(), args.config.warp_strength.abs());
+        write_rgb_png(BufWriter::new(fs::File::create(path)?), &colors)?;
+    }
 
-    let mut buf = vec![0u8; HEIGHT * WIDTH];
-    for i in 2..HEIGHT-2 { for j in 2..WIDTH-2 {
-        buf[i * WIDTH + j] = (res[[i, j]].clamp(-1., 1.) * 28. + 28.).round() as u8;
-    }}
+    for target in &args.outputs {
+        target.write(&res)?;
+        if target.format == OutputFormat::Raw {
+            eprintln!("wrote {} × {} little-endian f32, row-major, to {}",
+                args.config.width, args.config.height, target.path.display());
+        }
+    }
 
-    let mut writer = File::create("output.png")?;
-    let encoder = PngEncoder::new(&mut writer);
-    encoder.write_image(&buf, WIDTH as _, HEIGHT as _, image::ColorType::L8)?;
+    if args.mipmaps {
+        for (k, level) in mip_chain(&res).iter().enumerate().skip(1) {
+            for target in &args.outputs {
+                let stem = target.path.file_stem().unwrap_or_default().to_string_lossy();
+                let mut name = format!("{stem}_mip_{k}");
+                if let Some(ext) = target.path.extension() {
+                    name = format!("{name}.{}", ext.to_string_lossy());
+                }
+                let scale = target.scale.map(|scale| scale.resampled(0.5f32.powi(k as i32)));
+                OutputTarget { path: target.path.with_file_name(name), scale, ..target.clone() }.write(level)?;
+            }
+        }
+    }
 
     Ok(())
 }
@@ -0,0 +1,110 @@
+use ndarray::{Array2, Array3};
+use rand::{seq::SliceRandom, Rng};
+
+use crate::{kernel::{unit_vector, Lattice, Point, SIMPLEX_F2}, Fade, NoiseKind};
+
+pub(crate) type Point3 = [f32; 3];
+
+impl NoiseKind {
+    /// Dimensions of the gradient grid needed to sample `h` × `w` cells, or `None` if the
+    /// counts aren't finite or don't fit in a `u32`.
+    pub(crate) fn grid_dims(self, h: f32, w: f32) -> Option<[usize; 2]> {
+        let lines = |cells: f32, extra: usize| {
+            (0. ..=u32::MAX as f32).contains(&cells).then(|| (cells as usize).checked_add(extra)).flatten()
+        };
+        match self {
+            NoiseKind::Perlin | NoiseKind::Worley(_) | NoiseKind::Value => {
+                Some([lines(h.ceil(), 1)?, lines(w.ceil(), 1)?])
+            }
+            NoiseKind::Simplex => {
+                // lattice points are looked up in skewed coordinates, which run past the cell counts
+                let skew = (h + w) * SIMPLEX_F2;
+                Some([lines(h + skew, 2)?, lines(w + skew, 2)?])
+            }
+        }
+    }
+
+    /// Draws the value of one grid point: a unit gradient, a Worley feature point's offset
+    /// within its cell, or a value for value noise. Gradient angles are rounded to one of a
+    /// table's if `table` is set, see [`unit_vector`].
+    pub(crate) fn random_point(self, rng: &mut impl Rng, table: bool) -> Point {
+        match self {
+            NoiseKind::Perlin | NoiseKind::Simplex => unit_vector(rng.gen(), table),
+            NoiseKind::Worley(_) => (rng.gen(), rng.gen()),
+            NoiseKind::Value => (rng.gen::<f32>() * 2. - 1., 0.),
+        }
+    }
+}
+
+/// Where the gradients of [`NoiseKind::Perlin`] and [`NoiseKind::Simplex`] come from.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "config-file", derive(serde::Deserialize), serde(rename_all = "kebab-case"))]
+pub enum GradientSource {
+    /// A random angle per lattice point, turned into a vector with `sin_cos`, drawn from the RNG
+    /// in row-major order, so every gradient depends on the size of the map. This was the
+    /// default before [`Hashed`](Self::Hashed), and reproduces maps made then.
+    RandomAngles,
+    /// One of 8 fixed directions per lattice point, picked through a permutation table the RNG
+    /// shuffles for each octave, as in Perlin's reference implementation. Cheaper to fill, and
+    /// the same on every platform since no trigonometry is involved; the table repeats every
+    /// 256 cells.
+    Table,
+    /// A random angle per lattice point, hashed from the seed, the octave and the point's
+    /// absolute position instead of drawn in sequence, so maps with different
+    /// [`origin`](crate::HeightmapConfig::origin) or size share gradients where they overlap.
+    /// This also covers Worley feature points.
+    #[default]
+    Hashed,
+}
+
+/// A shuffled permutation of `0..=255`.
+pub(crate) fn permutation(rng: &mut impl Rng) -> [u8; 256] {
+    let mut perm = [0u8; 256];
+    for (k, p) in perm.iter_mut().enumerate() {
+        *p = k as u8;
+    }
+    perm.shuffle(rng);
+    perm
+}
+
+/// Points past either end of the grid wrap around, which makes tileable grids periodic and
+/// keeps rounding at the far edges of other grids from panicking.
+impl Lattice for Array2<Point> {
+    fn at(&self, ix: usize, iy: usize) -> Point {
+        let (rows, cols) = self.dim();
+        self[[ix % rows, iy % cols]]
+    }
+
+    fn at_signed(&self, ix: isize, iy: isize) -> Point {
+        let (rows, cols) = self.dim();
+        self[[ix.rem_euclid(rows as isize) as usize, iy.rem_euclid(cols as isize) as usize]]
+    }
+}
+
+/// A uniformly distributed random unit vector.
+pub(crate) fn random_gradient3(rng: &mut impl Rng) -> Point3 {
+    let z = rng.gen::<f32>() * 2. - 1.;
+    let (s, c) = (rng.gen::<f32>() * std::f32::consts::TAU).sin_cos();
+    let r = (1. - z * z).sqrt();
+    [r * c, r * s, z]
+}
+
+fn dot_grid_gradient3(grid: &Array3<Point3>, [ix, iy, iz]: [usize; 3], [x, y, z]: [f32; 3]) -> f32 {
+    let (nx, ny, nz) = grid.dim();
+    let g = grid[[ix % nx, iy % ny, iz % nz]];
+    (x - ix as f32) * g[0] + (y - iy as f32) * g[1] + (z - iz as f32) * g[2]
+}
+
+/// Three-dimensional Perlin noise; lattice points past the end of the grid wrap around like
+/// in [`perlin`](crate::kernel::perlin).
+pub(crate) fn perlin3(src: &Array3<Point3>, x: f32, y: f32, z: f32, fade: Fade) -> f32 {
+    let (x0, y0, z0) = (x as usize, y as usize, z as usize);
+    let (sx, sy, sz) = (x - x0 as f32, y - y0 as f32, z - z0 as f32);
+    let p = [x, y, z];
+
+    let corner = |dx, dy, dz| dot_grid_gradient3(src, [x0 + dx, y0 + dy, z0 + dz], p);
+    let edge = |dy, dz| fade.interpolate(corner(0, dy, dz), corner(1, dy, dz), sx);
+    let face = |dz| fade.interpolate(edge(0, dz), edge(1, dz), sy);
+    fade.interpolate(face(0), face(1), sz)
+}
+
@@ -0,0 +1,684 @@
+use std::{fs::File, io::{self, BufWriter, Cursor, Write}, path::{Path, PathBuf}};
+
+use exr::prelude::{
+    AnyChannel, AnyChannels, AttributeValue, FlatSamples, Image, SmallVec, Text, Vec2, WritableImage,
+};
+use image::{
+    codecs::{bmp::BmpEncoder, jpeg::JpegEncoder, png::PngEncoder, tiff::TiffEncoder}, ColorType, ImageEncoder,
+    ImageResult,
+};
+use ndarray::Array2;
+
+use crate::{
+    ambient_occlusion, classify, derived::gradient, flow_colors, flow_field, land_mask, normal_map,
+    packed_map, par::map_indexed, resize, slope_map, ColorRamp, HeightmapConfig,
+};
+
+/// Pixels along each edge of the image that are left at zero.
+const BORDER: usize = 2;
+
+/// Sample depth of grayscale PNG output.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BitDepth {
+    /// 8-bit samples; the clamp range, `-1..1` by default, maps onto the 57 levels `0..=56`.
+    #[default]
+    Eight,
+    /// 16-bit samples; the clamp range maps onto the full `0..=65535` range.
+    Sixteen,
+}
+
+impl BitDepth {
+    /// The sample `v` is written as, after clamping it to `lo..=hi`.
+    fn quantize(self, v: f32, (lo, hi): (f32, f32)) -> f32 {
+        let half = match self {
+            BitDepth::Eight => 28.,
+            BitDepth::Sixteen => u16::MAX as f32 / 2.,
+        };
+        // relative to the middle of the range, so the default one maps exactly as before
+        let (mid, radius) = ((lo + hi) / 2., (hi - lo) / 2.);
+        ((v.clamp(lo, hi) - mid) / radius * half + half).round()
+    }
+}
+
+/// PNG samples of the rows of a `height` rows tall map starting at row `first`, with the border
+/// left at zero.
+fn png_samples(rows: &Array2<f32>, depth: BitDepth, range: (f32, f32), first: usize, height: usize) -> Array2<f32> {
+    let width = rows.ncols();
+    let inside = |(i, j): (usize, usize)| {
+        (BORDER..height.saturating_sub(BORDER)).contains(&(first + i))
+            && (BORDER..width.saturating_sub(BORDER)).contains(&j)
+    };
+    map_indexed(rows, |ij, v| if inside(ij) { depth.quantize(v, range) } else { 0. })
+}
+
+/// File format of image outputs.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ImageEncoding {
+    #[default]
+    Png,
+    /// Lossy, at quality 90, for quick previews; 16-bit samples are cut down to 8 bits.
+    Jpeg,
+    /// 16-bit samples are cut down to 8 bits.
+    Bmp,
+    Tiff,
+}
+
+impl ImageEncoding {
+    /// The encoding named by the extension of `path`: `.jpg` or `.jpeg`, `.bmp`, `.tif` or
+    /// `.tiff`, in any case, and PNG for anything else.
+    pub fn from_path(path: &Path) -> Self {
+        let ext = path.extension().map(|e| e.to_string_lossy().to_ascii_lowercase());
+        match ext.as_deref() {
+            Some("jpg" | "jpeg") => ImageEncoding::Jpeg,
+            Some("bmp") => ImageEncoding::Bmp,
+            Some("tif" | "tiff") => ImageEncoding::Tiff,
+            _ => ImageEncoding::Png,
+        }
+    }
+
+    /// Encodes `buf`, samples of `color` in row-major order, with 16-bit samples in native byte
+    /// order as the `image` encoders take them.
+    fn encode<W: Write>(self, mut writer: W, buf: &[u8], width: usize, height: usize, color: ColorType)
+        -> ImageResult<()> {
+        let wide = matches!(color, ColorType::L16 | ColorType::La16);
+        if matches!(self, ImageEncoding::Jpeg | ImageEncoding::Bmp) && wide {
+            let high: Vec<u8> =
+                buf.chunks_exact(2).map(|s| (u16::from_ne_bytes([s[0], s[1]]) >> 8) as u8).collect();
+            let color = if color == ColorType::L16 { ColorType::L8 } else { ColorType::La8 };
+            return self.encode(writer, &high, width, height, color);
+        }
+        // JPEG has no alpha channel, so it's dropped
+        if self == ImageEncoding::Jpeg && matches!(color, ColorType::La8 | ColorType::Rgba8) {
+            let channels = color.channel_count() as usize;
+            let opaque: Vec<u8> = buf.chunks_exact(channels).flat_map(|p| &p[..channels - 1]).copied().collect();
+            let color = if color == ColorType::La8 { ColorType::L8 } else { ColorType::Rgb8 };
+            return self.encode(writer, &opaque, width, height, color);
+        }
+
+        let (width, height) = (width as u32, height as u32);
+        match self {
+            ImageEncoding::Png => PngEncoder::new(writer).write_image(buf, width, height, color),
+            ImageEncoding::Jpeg => {
+                JpegEncoder::new_with_quality(writer, 90).write_image(buf, width, height, color)
+            }
+            ImageEncoding::Bmp => BmpEncoder::new(&mut writer).write_image(buf, width, height, color),
+            ImageEncoding::Tiff => {
+                // the TIFF encoder seeks back to fill in offsets, which standard output can't do
+                let mut file = Cursor::new(Vec::new());
+                TiffEncoder::new(&mut file).write_image(buf, width, height, color)?;
+                Ok(writer.write_all(file.get_ref())?)
+            }
+        }
+    }
+}
+
+/// Samples of grayscale height images, see [`write_png`].
+fn height_samples(map: &Array2<f32>, depth: BitDepth, range: (f32, f32)) -> (Vec<u8>, ColorType) {
+    let samples = png_samples(map, depth, range, 0, map.nrows());
+    match depth {
+        BitDepth::Eight => (samples.iter().map(|&s| s as u8).collect(), ColorType::L8),
+        BitDepth::Sixteen => (samples.iter().flat_map(|&s| (s as u16).to_ne_bytes()).collect(), ColorType::L16),
+    }
+}
+
+/// `buf`, samples of `color` in row-major order, with an alpha channel added that is opaque where
+/// `mask` is set and transparent elsewhere.
+fn add_alpha(buf: &[u8], color: ColorType, mask: &Array2<bool>) -> (Vec<u8>, ColorType) {
+    let (with_alpha, alpha_bytes) = match color {
+        ColorType::L8 => (ColorType::La8, 1),
+        ColorType::L16 => (ColorType::La16, 2),
+        ColorType::Rgb8 => (ColorType::Rgba8, 1),
+        _ => unreachable!("no alpha for {color:?}"),
+    };
+    let pixels = buf.chunks_exact(color.bytes_per_pixel() as usize);
+    let buf = pixels.zip(mask).flat_map(|(p, &m)| {
+        p.iter().copied().chain(std::iter::repeat_n(if m { u8::MAX } else { 0 }, alpha_bytes))
+    });
+    (buf.collect(), with_alpha)
+}
+
+/// Samples of [`write_gray_png`] images.
+fn gray_samples(image: &Array2<f32>) -> Vec<u8> {
+    image.iter().map(|v| (v.clamp(0., 1.) * 255.).round() as u8).collect()
+}
+
+/// Samples of [`write_mask_png`] images.
+fn mask_samples(mask: &Array2<bool>) -> Vec<u8> {
+    mask.iter().map(|&m| if m { 255 } else { 0 }).collect()
+}
+
+/// Writes `map` as a grayscale PNG, clamping heights to `range` and mapping it onto the sample
+/// range.
+pub fn write_png<W: Write>(writer: W, map: &Array2<f32>, depth: BitDepth, range: (f32, f32)) -> ImageResult<()> {
+    let (buf, color) = height_samples(map, depth, range);
+    ImageEncoding::Png.encode(writer, &buf, map.ncols(), map.nrows(), color)
+}
+
+/// Encodes `buf`, grayscale samples of `color` as [`ImageEncoding::encode`] takes them, as a PNG
+/// with a `tEXt` chunk for each `(keyword, text)` pair, which the `image` encoder can't write.
+fn encode_png_with_text<W: Write>(writer: W, buf: &[u8], width: usize, height: usize, color: ColorType,
+    text: &[(&str, String)]) -> ImageResult<()> {
+    let (png_color, depth) = match color {
+        ColorType::L8 => (png::ColorType::Grayscale, png::BitDepth::Eight),
+        ColorType::L16 => (png::ColorType::Grayscale, png::BitDepth::Sixteen),
+        ColorType::La8 => (png::ColorType::GrayscaleAlpha, png::BitDepth::Eight),
+        ColorType::La16 => (png::ColorType::GrayscaleAlpha, png::BitDepth::Sixteen),
+        _ => unreachable!("no grayscale samples in {color:?}"),
+    };
+    let mut encoder = png::Encoder::new(writer, width as _, height as _);
+    encoder.set_color(png_color);
+    encoder.set_depth(depth);
+    for (keyword, text) in text {
+        encoder.add_text_chunk(keyword.to_string(), text.clone()).map_err(io::Error::from)?;
+    }
+    let mut png = encoder.write_header().map_err(io::Error::from)?;
+    // PNG samples are big-endian
+    let buf: Vec<u8> = match depth {
+        png::BitDepth::Sixteen => {
+            buf.chunks_exact(2).flat_map(|s| u16::from_ne_bytes([s[0], s[1]]).to_be_bytes()).collect()
+        }
+        _ => buf.to_vec(),
+    };
+    Ok(png.write_image_data(&buf).map_err(io::Error::from)?)
+}
+
+/// Writes `map` as [`write_png`] does with an alpha channel added, opaque where `mask` is set and
+/// transparent elsewhere; a [`land_mask`](crate::land_mask) makes the sea see-through.
+///
+/// Panics if `mask` isn't the size of `map`.
+pub fn write_png_alpha<W: Write>(writer: W, map: &Array2<f32>, depth: BitDepth, range: (f32, f32),
+    mask: &Array2<bool>) -> ImageResult<()> {
+    assert_eq!(mask.dim(), map.dim(), "the mask must be the size of the map");
+    let (buf, color) = height_samples(map, depth, range);
+    let (buf, color) = add_alpha(&buf, color, mask);
+    ImageEncoding::Png.encode(writer, &buf, map.ncols(), map.nrows(), color)
+}
+
+/// Generates the map `config` describes and writes it as [`write_png`] would, `rows` rows at a
+/// time, so maps too large to hold in memory can still be written; see
+/// [`HeightmapConfig::generate_strips`] for the configurations this can't do and the panics.
+pub fn write_png_strips<W: Write>(writer: W, config: &HeightmapConfig, depth: BitDepth, rows: usize)
+    -> ImageResult<()> {
+    let mut encoder = png::Encoder::new(writer, config.width as _, config.height as _);
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(match depth {
+        BitDepth::Eight => png::BitDepth::Eight,
+        BitDepth::Sixteen => png::BitDepth::Sixteen,
+    });
+    let mut png = encoder.write_header().map_err(io::Error::from)?;
+    let mut stream = png.stream_writer().map_err(io::Error::from)?;
+
+    let mut first = 0;
+    for strip in config.generate_strips(rows) {
+        let samples = png_samples(&strip, depth, config.clamp_range, first, config.height);
+        let buf: Vec<u8> = match depth {
+            BitDepth::Eight => samples.iter().map(|&s| s as u8).collect(),
+            BitDepth::Sixteen => samples.iter().flat_map(|&s| (s as u16).to_be_bytes()).collect(),
+        };
+        stream.write_all(&buf)?;
+        first += strip.nrows();
+    }
+    Ok(stream.finish().map_err(io::Error::from)?)
+}
+
+/// Writes a map of values in `0..1`, such as a [`slope_map`](crate::slope_map), as an 8-bit
+/// grayscale PNG using the full `0..=255` range, with no border.
+pub fn write_gray_png<W: Write>(writer: W, image: &Array2<f32>) -> ImageResult<()> {
+    ImageEncoding::Png.encode(writer, &gray_samples(image), image.ncols(), image.nrows(), ColorType::L8)
+}
+
+/// Writes a mask, such as a [`land_mask`](crate::land_mask), as a black and white PNG with
+/// `true` white.
+pub fn write_mask_png<W: Write>(writer: W, mask: &Array2<bool>) -> ImageResult<()> {
+    ImageEncoding::Png.encode(writer, &mask_samples(mask), mask.ncols(), mask.nrows(), ColorType::L8)
+}
+
+/// Writes a map of class ids, such as one from [`classify`](crate::classify), as an indexed PNG
+/// with `palette` giving the colour of each class, so tools can read the ids back as they are.
+///
+/// Panics if `palette` is empty or has more than 256 colours, or a class has no colour in it.
+pub fn write_indexed_png<W: Write>(writer: W, classes: &Array2<u8>, palette: &[[u8; 3]]) -> ImageResult<()> {
+    assert!((1..=256).contains(&palette.len()), "a palette holds 1 to 256 colours");
+    assert!(classes.iter().all(|&c| (c as usize) < palette.len()), "every class needs a colour in the palette");
+    let mut encoder = png::Encoder::new(writer, classes.ncols() as _, classes.nrows() as _);
+    encoder.set_color(png::ColorType::Indexed);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_palette(palette.concat());
+    let mut png = encoder.write_header().map_err(io::Error::from)?;
+    let buf: Vec<u8> = classes.iter().copied().collect();
+    Ok(png.write_image_data(&buf).map_err(io::Error::from)?)
+}
+
+/// Writes an RGB image, such as a [`normal_map`](crate::normal_map), [`flow_colors`](crate::flow_colors)
+/// or a [`ColorRamp`](crate::ColorRamp) preview, as a PNG.
+pub fn write_rgb_png<W: Write>(writer: W, image: &Array2<[u8; 3]>) -> ImageResult<()> {
+    write_rgb(writer, image, None, ImageEncoding::Png)
+}
+
+/// Writes an RGB image as [`write_rgb_png`] does with an alpha channel added, opaque where
+/// `mask` is set and transparent elsewhere.
+///
+/// Panics if `mask` isn't the size of `image`.
+pub fn write_rgba_png<W: Write>(writer: W, image: &Array2<[u8; 3]>, mask: &Array2<bool>) -> ImageResult<()> {
+    assert_eq!(mask.dim(), image.dim(), "the mask must be the size of the image");
+    write_rgb(writer, image, Some(mask), ImageEncoding::Png)
+}
+
+fn write_rgb<W: Write>(writer: W, image: &Array2<[u8; 3]>, mask: Option<&Array2<bool>>, encoding: ImageEncoding)
+    -> ImageResult<()> {
+    let buf: Vec<u8> = image.iter().flatten().copied().collect();
+    let (buf, color) = match mask {
+        Some(mask) => add_alpha(&buf, ColorType::Rgb8, mask),
+        None => (buf, ColorType::Rgb8),
+    };
+    encoding.encode(writer, &buf, image.ncols(), image.nrows(), color)
+}
+
+/// Writes the unquantized heights of `map` as raw little-endian `f32`s in row-major order, with
+/// no header; the reader has to know the dimensions.
+///
+/// From NumPy: `np.fromfile(path, dtype="<f4").reshape(height, width)`.
+pub fn write_raw<W: Write>(mut writer: W, map: &Array2<f32>) -> io::Result<()> {
+    let mut buf = Vec::with_capacity(map.len() * 4);
+    for v in map.iter() {
+        buf.extend_from_slice(&v.to_le_bytes());
+    }
+    writer.write_all(&buf)
+}
+
+/// Writes `map` as a Wavefront OBJ mesh: one vertex per pixel and two triangles per square of
+/// four neighbouring pixels, with vertex normals if `normals` is set.
+///
+/// Columns run along x and rows down y, so the image reads the right way up from above, and
+/// heights are multiplied by `z_scale`; z is up, as in Blender. Lines are written one at a time,
+/// so wrap `writer` in a [`BufWriter`].
+pub fn write_obj<W: Write>(mut writer: W, map: &Array2<f32>, z_scale: f32, normals: bool) -> io::Result<()> {
+    let (height, width) = map.dim();
+
+    for ((i, j), v) in map.indexed_iter() {
+        writeln!(writer, "v {} {} {}", j, height - 1 - i, v * z_scale)?;
+    }
+    if normals {
+        for i in 0..height { for j in 0..width {
+            // the same normals as `normal_map`, since y runs against the rows
+            let (gi, gj) = gradient(map, i, j, (false, false));
+            let (nx, ny, nz) = (-gj * z_scale, gi * z_scale, 1.);
+            let len = (nx * nx + ny * ny + nz * nz).sqrt();
+            writeln!(writer, "vn {} {} {}", nx / len, ny / len, nz / len)?;
+        }}
+    }
+
+    // OBJ indices start at 1; triangles wind counter-clockwise seen from above
+    let index = |i: usize, j: usize| i * width + j + 1;
+    for i in 0..height.saturating_sub(1) { for j in 0..width.saturating_sub(1) {
+        let (a, b, c, d) = (index(i, j), index(i, j + 1), index(i + 1, j), index(i + 1, j + 1));
+        for [x, y, z] in [[a, c, d], [a, d, b]] {
+            if normals {
+                writeln!(writer, "f {x}//{x} {y}//{y} {z}//{z}")?;
+            } else {
+                writeln!(writer, "f {x} {y} {z}")?;
+            }
+        }
+    }}
+    Ok(())
+}
+
+/// Byte order of [`write_r16`] and [`write_s16`] samples.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ByteOrder {
+    /// Most significant byte first, what Unity's terrain importer expects by default.
+    #[default]
+    Big,
+    /// Least significant byte first, as Unreal expects.
+    Little,
+}
+
+/// Writes `map` as headerless 16-bit samples in row-major order, the `.r16` format terrain
+/// importers read, with `range` mapped onto `0..=65535` like 16-bit PNGs but no border.
+pub fn write_r16<W: Write>(mut writer: W, map: &Array2<f32>, order: ByteOrder, range: (f32, f32))
+    -> io::Result<()> {
+    let mut buf = Vec::with_capacity(map.len() * 2);
+    for &v in map.iter() {
+        let sample = BitDepth::Sixteen.quantize(v, range) as u16;
+        buf.extend_from_slice(&match order {
+            ByteOrder::Big => sample.to_be_bytes(),
+            ByteOrder::Little => sample.to_le_bytes(),
+        });
+    }
+    writer.write_all(&buf)
+}
+
+/// Writes `map` as headerless signed 16-bit samples in row-major order, keeping height 0 at
+/// sample 0 so tools that read signed data still tell land from sea. Heights are clamped to
+/// `range` and multiplied by `32767 / max(|lo|, |hi|)`, so the end of the range further from 0
+/// reaches `±32767`; in the default `-1..1`, one sample is `1 / 32767` of a unit of height.
+pub fn write_s16<W: Write>(mut writer: W, map: &Array2<f32>, order: ByteOrder, (lo, hi): (f32, f32))
+    -> io::Result<()> {
+    let scale = i16::MAX as f32 / lo.abs().max(hi.abs());
+    let mut buf = Vec::with_capacity(map.len() * 2);
+    for &v in map.iter() {
+        let sample = (v.clamp(lo, hi) * scale).round() as i16;
+        buf.extend_from_slice(&match order {
+            ByteOrder::Big => sample.to_be_bytes(),
+            ByteOrder::Little => sample.to_le_bytes(),
+        });
+    }
+    writer.write_all(&buf)
+}
+
+/// Writes `map` as a single-channel OpenEXR image of 32-bit float samples, named `Y` as
+/// grayscale images are, so no precision is lost. Heights are written as they are, or clamped to
+/// `range` first if one is given, like the quantized formats clamp them.
+pub fn write_exr<W: Write>(writer: W, map: &Array2<f32>, range: Option<(f32, f32)>) -> ImageResult<()> {
+    encode_exr(writer, map, range, None)
+}
+
+/// Encodes [`write_exr`] images, with `scale` as attributes, and the clamp range too if there is
+/// one; see [`WorldScale`].
+fn encode_exr<W: Write>(mut writer: W, map: &Array2<f32>, range: Option<(f32, f32)>, scale: Option<WorldScale>)
+    -> ImageResult<()> {
+    let samples = match range {
+        Some((lo, hi)) => map.iter().map(|v| v.clamp(lo, hi)).collect(),
+        None => map.iter().copied().collect(),
+    };
+    let channel = AnyChannel::new("Y", FlatSamples::F32(samples));
+    let channels = AnyChannels::sort(SmallVec::from_elem(channel, 1));
+    let mut image = Image::from_channels((map.ncols(), map.nrows()), channels);
+    if let Some(scale) = scale {
+        let attributes = &mut image.layer_data.attributes.other;
+        attributes.insert(Text::from("meters_per_pixel"), AttributeValue::F32(scale.meters_per_pixel));
+        attributes.insert(Text::from("meters_per_height_unit"), AttributeValue::F32(scale.meters_per_height_unit));
+        if let Some((lo, hi)) = range {
+            attributes.insert(Text::from("height_range"), AttributeValue::FloatVec2(Vec2(lo, hi)));
+        }
+    }
+
+    // the encoder seeks back to fill in offsets, like the TIFF one
+    let mut file = Cursor::new(Vec::new());
+    image.write().to_buffered(&mut file).map_err(io::Error::other)?;
+    Ok(writer.write_all(file.get_ref())?)
+}
+
+/// Renders `map` as text `width` characters wide, using the ramp ` .:-=+*#%@` from `-1` to
+/// `1`, for a quick look over a terminal. Rows are resampled to half as many as columns, since
+/// character cells are about twice as tall as they are wide.
+pub fn ascii_art(map: &Array2<f32>, width: usize) -> String {
+    const RAMP: &[u8] = b" .:-=+*#%@";
+
+    let (height, map_width) = map.dim();
+    let rows = ((width * height) as f32 / map_width as f32 / 2.).round().max(1.) as usize;
+    let small = resize(map, width, rows);
+
+    let mut res = String::with_capacity((width + 1) * rows);
+    for row in small.rows() {
+        for &v in row {
+            let k = ((v.clamp(-1., 1.) + 1.) / 2. * (RAMP.len() - 1) as f32).round() as usize;
+            res.push(RAMP[k] as char);
+        }
+        res.push('\n');
+    }
+    res
+}
+
+/// The real-world size of a heightmap's pixels and heights, which an [`OutputTarget`] writing
+/// heights writes along with them so the file describes itself: as `tEXt` chunks of height
+/// PNGs, as attributes of EXR images, and otherwise in a JSON sidecar, see
+/// [`write_scale_json`]. Each is keyed `meters_per_pixel`, `meters_per_height_unit` and
+/// `height_range`, the heights the lowest and highest samples of quantized formats stand for;
+/// raw files, unclamped EXR images and meshes hold heights as they are, and go without it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WorldScale {
+    /// Metres between the centres of neighbouring pixels, along both axes.
+    pub meters_per_pixel: f32,
+    /// Metres a height of 1 stands for, so a map running from -1 to 1 is twice this tall.
+    pub meters_per_height_unit: f32,
+}
+
+impl WorldScale {
+    /// Panics unless both scales are positive and finite.
+    pub fn new(meters_per_pixel: f32, meters_per_height_unit: f32) -> Self {
+        let valid = |m: f32| m > 0. && m.is_finite();
+        assert!(valid(meters_per_pixel) && valid(meters_per_height_unit), "scales must be positive and finite");
+        Self { meters_per_pixel, meters_per_height_unit }
+    }
+
+    /// The scale of a copy of the map resampled by `factor`, such as 0.5 for each level of a
+    /// [`mip_chain`](crate::mip_chain); the pixels grow as the map shrinks.
+    pub fn resampled(self, factor: f32) -> Self {
+        Self { meters_per_pixel: self.meters_per_pixel / factor, ..self }
+    }
+
+    /// The `(key, value)` pairs a PNG's text chunks hold.
+    fn text(&self, (lo, hi): (f32, f32)) -> [(&'static str, String); 3] {
+        [
+            ("meters_per_pixel", self.meters_per_pixel.to_string()),
+            ("meters_per_height_unit", self.meters_per_height_unit.to_string()),
+            ("height_range", format!("{lo} {hi}")),
+        ]
+    }
+}
+
+/// Writes `scale` as a JSON object, along with the `(width, height)` of the map in pixels and its
+/// `height_range` if the heights were quantized or clamped to one, as an [`OutputTarget`] writes
+/// it to a sidecar file; see [`WorldScale`].
+pub fn write_scale_json<W: Write>(mut writer: W, scale: &WorldScale, (width, height): (usize, usize),
+    range: Option<(f32, f32)>) -> io::Result<()> {
+    writeln!(writer, "{{")?;
+    writeln!(writer, "  \"width\": {width},")?;
+    writeln!(writer, "  \"height\": {height},")?;
+    writeln!(writer, "  \"meters_per_pixel\": {},", scale.meters_per_pixel)?;
+    write!(writer, "  \"meters_per_height_unit\": {}", scale.meters_per_height_unit)?;
+    if let Some((lo, hi)) = range {
+        write!(writer, ",\n  \"height_range\": [{lo}, {hi}]")?;
+    }
+    writeln!(writer, "\n}}")
+}
+
+/// Where an [`OutputTarget`] writing to `path` puts its scale sidecar: `path` with `.json`
+/// appended, such as `map.r16.json`.
+pub fn sidecar_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".json");
+    name.into()
+}
+
+/// What an [`OutputTarget`] writes.
+#[derive(Clone, Debug, PartialEq)]
+pub enum OutputFormat {
+    /// Grayscale heights, see [`write_png`].
+    Png(BitDepth),
+    /// Unquantized heights, see [`write_raw`].
+    Raw,
+    /// 16-bit heights for terrain importers, see [`write_r16`].
+    R16(ByteOrder),
+    /// Signed 16-bit heights with 0 kept at 0, see [`write_s16`].
+    S16(ByteOrder),
+    /// 32-bit float heights as OpenEXR, see [`write_exr`]; clamped to the target's range if
+    /// `clamped` is set.
+    Exr { clamped: bool },
+    /// An RGB preview coloured with a ramp.
+    Color(ColorRamp),
+    /// An RGB [`normal_map`] with the given strength.
+    Normals(f32),
+    /// A grayscale [`slope_map`].
+    Slope,
+    /// A [`flow_field`] coloured by direction, see [`flow_colors`].
+    Flow,
+    /// A grayscale [`ambient_occlusion`] map over `samples` directions out to `radius` pixels.
+    Occlusion { samples: usize, radius: f32 },
+    /// Height, slope and occlusion in the red, green and blue channels, see [`packed_map`]; the
+    /// occlusion is estimated over this many pixels.
+    Packed(f32),
+    /// A black and white [`land_mask`] at the given sea level.
+    Mask(f32),
+    /// Heights sorted into classes by the `(lowest height, class)` bands, see [`classify`], as
+    /// an indexed PNG with the palette colouring them, see [`write_indexed_png`]; other image
+    /// encodings get the colours alone.
+    Classes { bands: Vec<(f32, u8)>, palette: Vec<[u8; 3]> },
+    /// A triangle mesh with the given height scale and vertex normals, see [`write_obj`].
+    Obj(f32),
+}
+
+/// A file to write one rendering of a generated heightmap to.
+#[derive(Clone, Debug, PartialEq)]
+pub struct OutputTarget {
+    pub format: OutputFormat,
+    /// File to write; `-` writes to standard output instead.
+    pub path: PathBuf,
+    /// Heights that quantized formats map onto their sample range, clamping the rest; see
+    /// [`HeightmapConfig::clamp_range`](crate::HeightmapConfig::clamp_range).
+    pub range: (f32, f32),
+    /// Whether the map wraps around along `(x, y)`, so that normal, slope, flow and packed images
+    /// tile with it; see [`normal_map`].
+    pub wrap: (bool, bool),
+    /// Sea level below which height and colour images are transparent, if they get an alpha
+    /// channel at all; see [`write_png_alpha`]. Other formats ignore it, and JPEG drops it.
+    pub alpha: Option<f32>,
+    /// Real-world size of the pixels and heights, if it should be written with the map. Only
+    /// height formats, PNG, raw, r16, s16, EXR and OBJ, write it; the others ignore it.
+    pub scale: Option<WorldScale>,
+    /// Whether the scale goes to a JSON sidecar, see [`sidecar_path`], even where the file could
+    /// hold it itself. Files written to standard output never get one.
+    pub sidecar: bool,
+}
+
+impl OutputTarget {
+    pub fn new(format: OutputFormat, path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        Self { format, path, range: (-1., 1.), wrap: (false, false), alpha: None, scale: None, sidecar: false }
+    }
+
+    pub fn with_range(mut self, lo: f32, hi: f32) -> Self {
+        self.range = (lo, hi);
+        self
+    }
+
+    pub fn with_wrap(mut self, wrap_x: bool, wrap_y: bool) -> Self {
+        self.wrap = (wrap_x, wrap_y);
+        self
+    }
+
+    pub fn with_alpha(mut self, sea_level: Option<f32>) -> Self {
+        self.alpha = sea_level;
+        self
+    }
+
+    pub fn with_scale(mut self, scale: Option<WorldScale>, sidecar: bool) -> Self {
+        self.scale = scale;
+        self.sidecar = sidecar;
+        self
+    }
+
+    /// Whether the format writes heights, and so takes a [`scale`](Self::scale).
+    pub fn writes_heights(&self) -> bool {
+        matches!(
+            self.format,
+            OutputFormat::Png(_) | OutputFormat::Raw | OutputFormat::R16(_) | OutputFormat::S16(_)
+                | OutputFormat::Exr { .. } | OutputFormat::Obj(_)
+        )
+    }
+
+    /// The heights the written samples are quantized or clamped to, if they are.
+    fn height_range(&self) -> Option<(f32, f32)> {
+        match self.format {
+            OutputFormat::Png(_) | OutputFormat::R16(_) | OutputFormat::S16(_) => Some(self.range),
+            OutputFormat::Exr { clamped } => clamped.then_some(self.range),
+            _ => None,
+        }
+    }
+
+    /// Whether the file itself holds the scale: height images encoded as PNG, and EXR images.
+    fn embeds_scale(&self) -> bool {
+        match self.format {
+            OutputFormat::Png(_) => ImageEncoding::from_path(&self.path) == ImageEncoding::Png,
+            OutputFormat::Exr { .. } => true,
+            _ => false,
+        }
+    }
+
+    /// Writes the scale of a `width` × `height` map to the sidecar next to `path`, if the target
+    /// has a scale and the file can't hold it or [`sidecar`](Self::sidecar) is set. [`write`]
+    /// calls this; it is for maps written some other way, such as by [`write_png_strips`].
+    ///
+    /// [`write`]: Self::write
+    pub fn write_sidecar(&self, width: usize, height: usize) -> io::Result<()> {
+        match self.scale {
+            Some(scale) if self.writes_heights() && (self.sidecar || !self.embeds_scale())
+                && self.path != Path::new("-") => {
+                let mut writer = BufWriter::new(File::create(sidecar_path(&self.path))?);
+                write_scale_json(&mut writer, &scale, (width, height), self.height_range())?;
+                writer.flush()
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Creates or truncates the file at `path` and writes `map` to it, along with its scale if
+    /// there is one. Images are encoded in the format the extension of `path` names, see
+    /// [`ImageEncoding::from_path`].
+    pub fn write(&self, map: &Array2<f32>) -> ImageResult<()> {
+        let encoding = ImageEncoding::from_path(&self.path);
+        let file: Box<dyn Write> = if self.path == Path::new("-") {
+            Box::new(io::stdout().lock())
+        } else {
+            Box::new(File::create(&self.path)?)
+        };
+        let mut writer = BufWriter::new(file);
+        let (width, height) = (map.ncols(), map.nrows());
+        let mask = self.alpha.map(|sea_level| land_mask(map, sea_level));
+        match &self.format {
+            OutputFormat::Png(depth) => {
+                let (buf, color) = height_samples(map, *depth, self.range);
+                let (buf, color) = match &mask {
+                    Some(mask) => add_alpha(&buf, color, mask),
+                    None => (buf, color),
+                };
+                match self.scale {
+                    Some(scale) if encoding == ImageEncoding::Png => {
+                        encode_png_with_text(&mut writer, &buf, width, height, color, &scale.text(self.range))?
+                    }
+                    _ => encoding.encode(&mut writer, &buf, width, height, color)?,
+                }
+            }
+            OutputFormat::Raw => write_raw(&mut writer, map)?,
+            OutputFormat::R16(order) => write_r16(&mut writer, map, *order, self.range)?,
+            OutputFormat::S16(order) => write_s16(&mut writer, map, *order, self.range)?,
+            OutputFormat::Exr { clamped } => {
+                encode_exr(&mut writer, map, clamped.then_some(self.range), self.scale)?
+            }
+            OutputFormat::Color(ramp) => write_rgb(&mut writer, &ramp.apply(map), mask.as_ref(), encoding)?,
+            OutputFormat::Normals(strength) => {
+                write_rgb(&mut writer, &normal_map(map, *strength, self.wrap), None, encoding)?
+            }
+            OutputFormat::Slope => {
+                let slopes = slope_map(map, self.wrap);
+                encoding.encode(&mut writer, &gray_samples(&slopes), width, height, ColorType::L8)?
+            }
+            OutputFormat::Flow => {
+                write_rgb(&mut writer, &flow_colors(&flow_field(map, self.wrap)), None, encoding)?
+            }
+            OutputFormat::Occlusion { samples, radius } => {
+                let occlusion = ambient_occlusion(map, *samples, *radius, self.wrap);
+                encoding.encode(&mut writer, &gray_samples(&occlusion), width, height, ColorType::L8)?
+            }
+            OutputFormat::Packed(radius) => {
+                write_rgb(&mut writer, &packed_map(map, self.range, *radius, self.wrap), None, encoding)?
+            }
+            OutputFormat::Mask(sea_level) => {
+                let mask = land_mask(map, *sea_level);
+                encoding.encode(&mut writer, &mask_samples(&mask), width, height, ColorType::L8)?
+            }
+            OutputFormat::Classes { bands, palette } => {
+                let classes = classify(map, bands);
+                if encoding == ImageEncoding::Png {
+                    write_indexed_png(&mut writer, &classes, palette)?
+                } else {
+                    write_rgb(&mut writer, &classes.mapv(|c| palette[c as usize]), None, encoding)?
+                }
+            }
+            OutputFormat::Obj(z_scale) => write_obj(&mut writer, map, *z_scale, true)?,
+        }
+        writer.flush()?;
+        Ok(self.write_sidecar(width, height)?)
+    }
+}
@@ -0,0 +1,43 @@
+//! Loops that run on all cores with the `parallel` feature and serially without it. Each item
+//! is computed the same way either way, so the output doesn't depend on the feature.
+
+use ndarray::{Array, Array2, ArrayViewMut, Axis, Dimension, RemoveAxis, Zip};
+#[cfg(feature = "parallel")]
+use ndarray::parallel::prelude::*;
+
+/// Calls `f` with the index and contents of each subview along the first axis of `map`: the
+/// rows of a 2D map, or the slices of a 3D one.
+pub(crate) fn for_each_outer<D: RemoveAxis, F>(map: &mut Array<f32, D>, f: F)
+where F: Fn(usize, ArrayViewMut<f32, D::Smaller>) + Sync + Send {
+    #[cfg(feature = "parallel")]
+    map.axis_iter_mut(Axis(0)).into_par_iter().enumerate().for_each(|(k, view)| f(k, view));
+    #[cfg(not(feature = "parallel"))]
+    map.axis_iter_mut(Axis(0)).enumerate().for_each(|(k, view)| f(k, view));
+}
+
+/// Calls `f` with the index of and a reference to each element of `map`.
+pub(crate) fn for_each_indexed<D: Dimension + Copy, F>(map: &mut Array<f32, D>, f: F)
+where D::Pattern: Send, F: Fn(D::Pattern, &mut f32) + Sync + Send {
+    #[cfg(feature = "parallel")]
+    Zip::indexed(map).par_for_each(f);
+    #[cfg(not(feature = "parallel"))]
+    Zip::indexed(map).for_each(f);
+}
+
+/// Calls `f` with a reference to each element of `map`.
+pub(crate) fn for_each<D: Dimension, F>(map: &mut Array<f32, D>, f: F)
+where F: Fn(&mut f32) + Sync + Send {
+    #[cfg(feature = "parallel")]
+    Zip::from(map).par_for_each(f);
+    #[cfg(not(feature = "parallel"))]
+    Zip::from(map).for_each(f);
+}
+
+/// A map of `f` applied to the index and value of each element of `map`.
+pub(crate) fn map_indexed<T: Send, F>(map: &Array2<f32>, f: F) -> Array2<T>
+where F: Fn((usize, usize), f32) -> T + Sync + Send {
+    #[cfg(feature = "parallel")]
+    return Zip::indexed(map).par_map_collect(|ij, &v| f(ij, v));
+    #[cfg(not(feature = "parallel"))]
+    return Zip::indexed(map).map_collect(|ij, &v| f(ij, v));
+}
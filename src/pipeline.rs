@@ -0,0 +1,255 @@
+use std::{fmt, io};
+
+use image::ImageResult;
+use ndarray::Array2;
+
+use crate::{
+    generate::{fill_outside, substream, within_region, HYDRAULIC_STREAM},
+    hydraulic_erode,
+    kernel::NOISE_AMPLITUDE,
+    par::{for_each, for_each_indexed},
+    post::{blur, equalize, flatten, invert, stretch, terrace},
+    thermal_erode, ConfigError, Curve, HeightmapConfig, HydraulicParams, NoiseKind, Normalization, OutputTarget,
+    WorleyMode,
+};
+
+/// Tag of the seed crater centres are placed from.
+const CRATER_STREAM: u64 = 0xBE5466CF34E90C6C;
+
+/// Parameters of [`Stage::Craters`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Craters {
+    /// Size in pixels of the Worley cells craters are scattered over, one crater per cell, so
+    /// halving it makes four times as many.
+    pub cell_size: f32,
+    /// Rim radius of each crater as a fraction of `cell_size`; around 0.5, neighbouring
+    /// craters start to overlap.
+    pub radius: f32,
+    /// How far crater floors sink below the surrounding terrain.
+    pub depth: f32,
+    /// Steepness of crater walls and narrowness of their rims; at 2 the bowls are round, and
+    /// higher values flatten the floors.
+    pub sharpness: f32,
+}
+
+impl Default for Craters {
+    fn default() -> Self {
+        Self { cell_size: 48., radius: 0.4, depth: 0.4, sharpness: 3. }
+    }
+}
+
+/// Height of a rim above the terrain around a crater, as a fraction of the depth.
+const RIM: f32 = 0.25;
+
+impl Craters {
+    /// Carves craters into `map`: the distance from each pixel to its nearest crater centre, a
+    /// single octave of Worley F1 noise, is turned into a bowl with a raised rim and added on.
+    /// Pixels outside the config's region mask are left alone.
+    ///
+    /// Fails if the Worley noise can't be generated with cells of `cell_size`, leaving `map` as
+    /// it was.
+    fn carve(&self, config: &HeightmapConfig, map: &mut Array2<f32>) -> Result<(), ConfigError> {
+        let mask = config.region_mask.as_ref().filter(|mask| mask.dim() == map.dim());
+        let centres = HeightmapConfig {
+            width: map.ncols(),
+            height: map.nrows(),
+            octaves: 1,
+            perlin_weight: 1.,
+            seed: (config.seed.0 ^ CRATER_STREAM, config.seed.1),
+            feature_scale: (self.cell_size, self.cell_size),
+            noise: NoiseKind::Worley(WorleyMode::F1),
+            gradients: config.gradients,
+            normalization: Normalization::None,
+            wrap_x: config.wrap_x,
+            wrap_y: config.wrap_y,
+            origin: config.origin,
+            region_mask: mask.cloned(),
+            ..HeightmapConfig::default()
+        };
+        centres.validate()?;
+        let f1 = centres.generate();
+
+        let s = self.sharpness;
+        for_each_indexed(map, |ij, v| {
+            if mask.is_some_and(|mask| !mask[ij]) { return; }
+            // back from the noise range to the distance in cells
+            let r = (f1[ij] / NOISE_AMPLITUDE + 1.) / 2. / self.radius;
+            let bowl = if r < 1. { r.powf(s) - 1. } else { 0. };
+            let rim = RIM * (-((r - 1.) * s * 2.).powi(2)).exp();
+            *v += (bowl + rim) * self.depth;
+        });
+        Ok(())
+    }
+}
+
+/// An operation on a whole map, for [`Stage::Custom`].
+pub type MapFn = Box<dyn Fn(&mut Array2<f32>) + Send + Sync>;
+
+/// One step of a [`Pipeline`].
+pub enum Stage {
+    /// Remap the map onto `-1..1` by its lowest and highest values, like
+    /// [`Normalization::MinMax`](crate::Normalization::MinMax).
+    Stretch,
+    /// Spread heights evenly over `-1..1`, like
+    /// [`Normalization::Equalize`](crate::Normalization::Equalize).
+    Equalize,
+    /// `(levels, smoothness)` terracing, as for [`HeightmapConfig::terraces`].
+    Terraces(u32, f32),
+    /// Flatten heights below `below` and above `above` to those levels, for plains, ocean
+    /// floors and mesas, rounding the clamp off over a `band` of heights around each threshold
+    /// so the slopes meet the flats smoothly; with a band of 0 they meet at a sharp edge.
+    Flatten { below: Option<f32>, above: Option<f32>, band: f32 },
+    /// A [`thermal_erode`] pass of `(iterations, talus)`, wrapping around the edges the config
+    /// wraps around.
+    ThermalErosion(usize, f32),
+    /// A [`hydraulic_erode`] pass with this many droplets, drawn from the same RNG stream as
+    /// [`HeightmapConfig::hydraulic_erosion`] and wrapping like it.
+    HydraulicErosion(usize, HydraulicParams),
+    /// A Gaussian [`blur`] with this standard deviation in pixels.
+    Blur(f32),
+    /// Multiply every height by the first value, then add the second.
+    GainBias(f32, f32),
+    /// Flip the map within the config's [`clamp_range`](HeightmapConfig::clamp_range), like
+    /// [`Invert::AfterIsland`](crate::Invert::AfterIsland).
+    Invert,
+    /// Reshape heights within the config's [`clamp_range`](HeightmapConfig::clamp_range) by a
+    /// [`Curve`], like [`HeightmapConfig::curve`].
+    Curve(Curve),
+    /// Carve [`Craters`] scattered by Worley noise, seeded from the config's seed; see
+    /// [`Pipeline::cratered`].
+    Craters(Craters),
+    /// Write the map as it is at this point, without changing it.
+    Write(OutputTarget),
+    /// Any other operation.
+    Custom(MapFn),
+}
+
+impl fmt::Debug for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Stage::Stretch => write!(f, "Stretch"),
+            Stage::Equalize => write!(f, "Equalize"),
+            Stage::Terraces(levels, smoothness) => write!(f, "Terraces({levels}, {smoothness})"),
+            Stage::Flatten { below, above, band } => {
+                write!(f, "Flatten {{ below: {below:?}, above: {above:?}, band: {band} }}")
+            }
+            Stage::ThermalErosion(iterations, talus) => write!(f, "ThermalErosion({iterations}, {talus})"),
+            Stage::HydraulicErosion(droplets, params) => write!(f, "HydraulicErosion({droplets}, {params:?})"),
+            Stage::Blur(sigma) => write!(f, "Blur({sigma})"),
+            Stage::GainBias(gain, bias) => write!(f, "GainBias({gain}, {bias})"),
+            Stage::Invert => write!(f, "Invert"),
+            Stage::Curve(curve) => write!(f, "Curve({curve:?})"),
+            Stage::Craters(craters) => write!(f, "Craters({craters:?})"),
+            Stage::Write(target) => write!(f, "Write({target:?})"),
+            Stage::Custom(_) => write!(f, "Custom(..)"),
+        }
+    }
+}
+
+impl Stage {
+    fn apply(&self, config: &HeightmapConfig, map: &mut Array2<f32>) -> ImageResult<()> {
+        let mask = config.region_mask.as_ref().filter(|mask| mask.dim() == map.dim());
+        match self {
+            Stage::Stretch => match mask {
+                Some(mask) => within_region(mask, map, stretch),
+                None => stretch(map),
+            },
+            Stage::Equalize => match mask {
+                Some(mask) => within_region(mask, map, equalize),
+                None => equalize(map),
+            },
+            Stage::Terraces(levels, smoothness) => for_each(map, |v| *v = terrace(*v, *levels, *smoothness)),
+            Stage::Flatten { below, above, band } => for_each(map, |v| *v = flatten(*v, *below, *above, *band)),
+            Stage::ThermalErosion(iterations, talus) => {
+                thermal_erode(map, *iterations, *talus, (config.wrap_x, config.wrap_y))
+            }
+            Stage::HydraulicErosion(droplets, params) => {
+                let wrap = (config.wrap_x, config.wrap_y);
+                hydraulic_erode(map, *droplets, params, wrap, &mut substream(config.seed, HYDRAULIC_STREAM))
+            }
+            Stage::Blur(sigma) => blur(map, *sigma),
+            Stage::GainBias(gain, bias) => for_each(map, |v| *v = *v * gain + bias),
+            Stage::Invert => for_each(map, |v| *v = invert(*v, config.clamp_range)),
+            Stage::Curve(curve) => for_each(map, |v| *v = curve.apply(*v, config.clamp_range)),
+            // reported like the EXR encoder's errors, as the image crate has no kind for them
+            Stage::Craters(craters) => {
+                craters.carve(config, map).map_err(|e| io::Error::other(format!("can't carve craters: {e}")))?
+            }
+            Stage::Write(target) => target.write(map)?,
+            Stage::Custom(f) => f(map),
+        }
+        if mask.is_some() {
+            fill_outside(config, map);
+        }
+        Ok(())
+    }
+}
+
+/// An ordered list of stages run on a freshly generated map, for chaining operations in an
+/// order [`HeightmapConfig`]'s fixed one doesn't allow:
+///
+/// ```
+/// use heightmap_gen::{HeightmapConfig, HydraulicParams, Pipeline, Stage};
+///
+/// let config = HeightmapConfig::default().with_size(64, 64).with_warp_strength(8.);
+/// let pipeline = Pipeline::new()
+///     .then(Stage::Stretch)
+///     .then(Stage::HydraulicErosion(500, HydraulicParams::default()))
+///     .then(Stage::Terraces(8, 0.3))
+///     .then(Stage::Custom(Box::new(|map| map.mapv_inplace(|v| v.max(-0.2)))));
+/// let map = pipeline.run(&config).unwrap();
+/// ```
+///
+/// Each stage comes with an enabled flag, and `stages` can be reordered freely.
+#[derive(Debug, Default)]
+pub struct Pipeline {
+    pub stages: Vec<(Stage, bool)>,
+}
+
+impl Pipeline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The cratered preset: Worley F1 craters carved into the generated terrain, for lunar or
+    /// volcanic landscapes. The fbm Perlin octaves of the default config give the surface detail
+    /// between them.
+    ///
+    /// ```
+    /// use heightmap_gen::{Craters, HeightmapConfig, Pipeline};
+    ///
+    /// let craters = Craters { cell_size: 24., depth: 0.6, ..Craters::default() };
+    /// let map = Pipeline::cratered(craters).run(&HeightmapConfig::default().with_size(128, 128)).unwrap();
+    /// ```
+    pub fn cratered(craters: Craters) -> Self {
+        Self::new().then(Stage::Craters(craters))
+    }
+
+    /// Appends an enabled stage.
+    pub fn then(mut self, stage: Stage) -> Self {
+        self.stages.push((stage, true));
+        self
+    }
+
+    /// Enables or disables the stage at `index`.
+    ///
+    /// Panics if there is no such stage.
+    pub fn with_enabled(mut self, index: usize, enabled: bool) -> Self {
+        self.stages[index].1 = enabled;
+        self
+    }
+
+    /// Generates a map from `config`, including any stages `config` itself enables, then runs
+    /// the enabled stages over it in order. Fails if a [`Stage::Write`] does, or if
+    /// [`Stage::Craters`] have a cell size no Worley noise can be generated at.
+    ///
+    /// With a [`region_mask`](HeightmapConfig::region_mask), stretching and equalizing only see
+    /// the pixels inside it, and the pixels outside are set back to the fill after every stage.
+    pub fn run(&self, config: &HeightmapConfig) -> ImageResult<Array2<f32>> {
+        let mut map = config.generate();
+        for (stage, _) in self.stages.iter().filter(|(_, enabled)| *enabled) {
+            stage.apply(config, &mut map)?;
+        }
+        Ok(map)
+    }
+}
@@ -0,0 +1,262 @@
+use std::{error::Error, fmt};
+
+use ndarray::{s, Array, Array2, Dimension, Zip};
+
+use crate::par::{for_each, for_each_indexed};
+
+/// How the summed octaves are scaled before the rest of the pipeline runs.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "config-file", derive(serde::Deserialize), serde(rename_all = "kebab-case"))]
+pub enum Normalization {
+    /// Divide by the total octave amplitude and recentre the noise style on zero. The result
+    /// stays roughly in `-1..1` but rarely spans all of it.
+    #[default]
+    ScaleSum,
+    /// Linearly map the actual lowest and highest sums in the map onto `-1` and `1`, for full
+    /// contrast.
+    MinMax,
+    /// Leave the raw weighted sums, for compositing layers by hand.
+    None,
+    /// Remap the sums through their own distribution so heights are spread evenly over
+    /// `-1..1`, using the whole tonal range instead of clustering around the middle. Equal sums
+    /// always get the same height.
+    Equalize,
+}
+
+/// Which stage of [`HeightmapConfig::invert`](crate::HeightmapConfig::invert) flips the map
+/// upside down at, turning peaks into valleys.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "config-file", derive(serde::Deserialize), serde(rename_all = "kebab-case"))]
+pub enum Invert {
+    /// Flip the normalized noise around 0 before the squircle blend and island falloff, which
+    /// still sink the edges; an island of basins rather than peaks.
+    BeforeIsland,
+    /// Flip the finished map within [`clamp_range`](crate::HeightmapConfig::clamp_range), after
+    /// the falloffs, erosion, gain and bias, so its quantized output is the negative of the
+    /// uninverted map's and raised edges surround a basin.
+    #[default]
+    AfterIsland,
+}
+
+/// A response curve that reshapes the spread of finished heights without touching their
+/// frequency content, for [`HeightmapConfig::curve`](crate::HeightmapConfig::curve). Both kinds
+/// work on a height's position `t` within the clamp range, 0 at its low end and 1 at its high
+/// end, and leave heights outside it as they are.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "config-file", derive(serde::Deserialize), serde(rename_all = "kebab-case"))]
+pub enum Curve {
+    /// `t^gamma`: gammas above 1 push the middle heights down into broad lowlands under sharp
+    /// peaks, and ones below 1 raise them into plateaus. A gamma of 1 changes nothing.
+    Gamma(f32),
+    /// Straight lines through `(t, new t)` control points sorted by `t`, as in the curves tool
+    /// of an image editor. Before the first point and after the last, heights are moved as far
+    /// as that point moves them.
+    Points(Vec<(f32, f32)>),
+}
+
+impl Curve {
+    /// Whether the curve is usable: a positive gamma, or at least one control point, all finite,
+    /// in strictly increasing order of `t`.
+    pub(crate) fn is_valid(&self) -> bool {
+        match self {
+            Curve::Gamma(gamma) => *gamma > 0. && gamma.is_finite(),
+            Curve::Points(points) => {
+                !points.is_empty() && points.iter().all(|(t, u)| t.is_finite() && u.is_finite())
+                    && points.windows(2).all(|w| w[0].0 < w[1].0)
+            }
+        }
+    }
+
+    /// Height `v` after the curve, with `(lo, hi)` the clamp range.
+    pub(crate) fn apply(&self, v: f32, (lo, hi): (f32, f32)) -> f32 {
+        let t = (v - lo) / (hi - lo);
+        if !(0. ..=1.).contains(&t) || *self == Curve::Gamma(1.) { return v; }
+        let t = match self {
+            Curve::Gamma(gamma) => t.powf(*gamma),
+            Curve::Points(points) => {
+                let (first, last) = (points[0], points[points.len() - 1]);
+                if t <= first.0 {
+                    t + first.1 - first.0
+                } else if t >= last.0 {
+                    t + last.1 - last.0
+                } else {
+                    let k = points.partition_point(|p| p.0 <= t);
+                    let ((t0, u0), (t1, u1)) = (points[k - 1], points[k]);
+                    u0 + (u1 - u0) * (t - t0) / (t1 - t0)
+                }
+            }
+        };
+        lo + t * (hi - lo)
+    }
+
+    /// The slope of [`apply`](Self::apply) at `v`.
+    pub(crate) fn slope(&self, v: f32, (lo, hi): (f32, f32)) -> f32 {
+        let t = (v - lo) / (hi - lo);
+        if !(0. ..=1.).contains(&t) { return 1.; }
+        match self {
+            Curve::Gamma(gamma) => gamma * t.powf(gamma - 1.),
+            Curve::Points(points) => {
+                let k = points.partition_point(|p| p.0 <= t);
+                if k == 0 || k == points.len() { return 1.; }
+                let ((t0, u0), (t1, u1)) = (points[k - 1], points[k]);
+                (u1 - u0) / (t1 - t0)
+            }
+        }
+    }
+}
+
+/// The lowest and highest values in `map`, or `(0, 0)` if it is empty.
+pub(crate) fn min_max<D: Dimension>(map: &Array<f32, D>) -> (f32, f32) {
+    map.iter().fold(None, |acc, &v| match acc {
+        None => Some((v, v)),
+        Some((lo, hi)) => Some((v.min(lo), v.max(hi))),
+    }).unwrap_or((0., 0.))
+}
+
+/// Remaps `map` so its lowest value becomes `-1` and its highest `1`; a flat map becomes 0.
+pub(crate) fn stretch<D: Dimension>(map: &mut Array<f32, D>) {
+    let (lo, hi) = min_max(map);
+    let span = hi - lo;
+    for_each(map, |v| *v = if span > 0. { (*v - lo) / span * 2. - 1. } else { 0. });
+}
+
+/// Histogram equalization: replaces each value of `map` with its rank among all the values,
+/// scaled onto `-1..=1`. Tied values share the mean of their ranks, so the result doesn't
+/// depend on the order they were visited in.
+pub(crate) fn equalize<D: Dimension>(map: &mut Array<f32, D>) {
+    let mut sorted: Vec<f32> = map.iter().copied().collect();
+    sorted.sort_unstable_by(f32::total_cmp);
+    let last = sorted.len().saturating_sub(1).max(1) as f32;
+
+    for_each(map, |v| {
+        let lo = sorted.partition_point(|x| x.total_cmp(v).is_lt());
+        let hi = sorted.partition_point(|x| x.total_cmp(v).is_le());
+        *v = (lo + hi - 1) as f32 / last - 1.;
+    });
+}
+
+/// Snaps `v` towards the nearest of `levels` evenly spaced heights in `-1..=1`; `smoothness`
+/// blends back towards the original height, with 0 leaving only the terrace levels.
+pub(crate) fn terrace(v: f32, levels: u32, smoothness: f32) -> f32 {
+    let steps = levels.saturating_sub(1);
+    let snapped = if steps == 0 {
+        0.
+    } else {
+        let step = 2. / steps as f32;
+        ((v.clamp(-1., 1.) + 1.) / step).round() * step - 1.
+    };
+    snapped + (v - snapped) * smoothness
+}
+
+/// `v` raised to at least `below` and lowered to at most `above`, where they are given, so the
+/// heights past them become flat plains and plateaus. Within `band / 2` of a threshold the
+/// clamp is rounded off with a quadratic, so slopes run smoothly onto the flat instead of
+/// stopping at a crease; a band of 0 clamps sharply.
+pub(crate) fn flatten(v: f32, below: Option<f32>, above: Option<f32>, band: f32) -> f32 {
+    // the polynomial smooth maximum, which is exact more than half a band from the threshold
+    let smooth_max = |a: f32, b: f32| {
+        if band <= 0. { return a.max(b); }
+        let h = (0.5 + (a - b) / band).clamp(0., 1.);
+        a * h + b * (1. - h) + band / 2. * h * (1. - h)
+    };
+    let v = below.map_or(v, |level| smooth_max(v, level));
+    above.map_or(v, |level| -smooth_max(-v, -level))
+}
+
+/// `v` flipped within `(lo, hi)`, so `lo` and `hi` trade places.
+pub(crate) fn invert(v: f32, (lo, hi): (f32, f32)) -> f32 {
+    lo + hi - v
+}
+
+/// Smooths `map` with a Gaussian blur of standard deviation `sigma` pixels, run as a pass along
+/// the rows and then one along the columns. Pixels past the edges take the value of the nearest
+/// edge pixel. A `sigma` of 0 or less leaves the map as it is.
+pub fn blur(map: &mut Array2<f32>, sigma: f32) {
+    if sigma <= 0. || map.is_empty() { return; }
+
+    // three standard deviations either side cover all but 0.3% of the weight
+    let radius = (sigma * 3.).ceil() as isize;
+    let mut kernel: Vec<f32> =
+        (-radius..=radius).map(|k| (-(k * k) as f32 / (2. * sigma * sigma)).exp()).collect();
+    let total: f32 = kernel.iter().sum();
+    kernel.iter_mut().for_each(|w| *w /= total);
+
+    let (height, width) = map.dim();
+    let clamped = |p: usize, k: usize, len: usize| (p + k).saturating_sub(radius as usize).min(len - 1);
+    let src = map.clone();
+    for_each_indexed(map, |(i, j), v| {
+        *v = kernel.iter().enumerate().map(|(k, w)| w * src[[i, clamped(j, k, width)]]).sum();
+    });
+    let src = map.clone();
+    for_each_indexed(map, |(i, j), v| {
+        *v = kernel.iter().enumerate().map(|(k, w)| w * src[[clamped(i, k, height), j]]).sum();
+    });
+}
+
+/// Composites two layers through a mask, `a * (1 - m) + b * m` per pixel, with the mask's
+/// weights clamped to `0..1`.
+///
+/// Layers are just maps from separately configured generators, for example a coarse continent
+/// layer, a fine detail layer, and a third map deciding where the detail shows:
+///
+/// ```
+/// use heightmap_gen::{blend, HeightmapConfig};
+///
+/// let base = HeightmapConfig::default().with_size(64, 64);
+/// let continent = base.clone().with_octaves(3).generate();
+/// let detail = base.clone().with_seed((1, 2)).generate();
+/// // generated heights run from -1 to 1, so shift the mask into 0..1
+/// let mask = base.with_seed((3, 4)).with_perlin_weight(1.).generate().mapv(|m| (m + 1.) / 2.);
+///
+/// let map = blend(&continent, &detail, &mask);
+/// ```
+///
+/// Panics if the three maps differ in size.
+pub fn blend(a: &Array2<f32>, b: &Array2<f32>, mask: &Array2<f32>) -> Array2<f32> {
+    Zip::from(a).and(b).and(mask).map_collect(|&a, &b, &m| {
+        let m = m.clamp(0., 1.);
+        a * (1. - m) + b * m
+    })
+}
+
+/// Why [`stitch`] couldn't lay tiles out.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum StitchError {
+    /// The grid was asked to have no columns.
+    NoColumns,
+    /// Tile `index` is `found` (rows, columns) while the first tile is `expected`.
+    SizeMismatch { index: usize, expected: (usize, usize), found: (usize, usize) },
+}
+
+impl fmt::Display for StitchError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StitchError::NoColumns => write!(f, "tiles can't be stitched into zero columns"),
+            StitchError::SizeMismatch { index, expected, found } => write!(f,
+                "tile {index} is {} × {} but the first tile is {} × {}", found.1, found.0, expected.1, expected.0),
+        }
+    }
+}
+
+impl Error for StitchError {}
+
+/// Lays `tiles` out row by row in a grid `cols` tiles wide, for checking that maps generated at
+/// neighbouring [`origin`](crate::HeightmapConfig::origin)s or with wrapping join up. A last
+/// row with fewer than `cols` tiles is padded with zeros.
+pub fn stitch(tiles: &[&Array2<f32>], cols: usize) -> Result<Array2<f32>, StitchError> {
+    if cols == 0 { return Err(StitchError::NoColumns); }
+    let Some(first) = tiles.first() else { return Ok(Array2::zeros([0, 0])) };
+
+    let (h, w) = first.dim();
+    if let Some(index) = tiles.iter().position(|t| t.dim() != (h, w)) {
+        return Err(StitchError::SizeMismatch { index, expected: (h, w), found: tiles[index].dim() });
+    }
+
+    let rows = tiles.len().div_ceil(cols);
+    let mut res = Array2::zeros([rows * h, cols.min(tiles.len()) * w]);
+    for (k, tile) in tiles.iter().enumerate() {
+        let (r, c) = (k / cols, k % cols);
+        res.slice_mut(s![r * h..(r + 1) * h, c * w..(c + 1) * w]).assign(*tile);
+    }
+    Ok(res)
+}
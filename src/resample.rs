@@ -0,0 +1,57 @@
+use ndarray::{s, Array2};
+
+/// The height at column `fx` and row `fy` of `map`, interpolated bilinearly between the four
+/// nearest pixels. Coordinates outside the map are clamped to its edges.
+///
+/// Panics if `map` is empty.
+pub fn sample_bilinear(map: &Array2<f32>, fx: f32, fy: f32) -> f32 {
+    let (height, width) = map.dim();
+    let x = fx.clamp(0., (width - 1) as f32);
+    let y = fy.clamp(0., (height - 1) as f32);
+
+    let (x0, y0) = (x as usize, y as usize);
+    let (x1, y1) = ((x0 + 1).min(width - 1), (y0 + 1).min(height - 1));
+    let (sx, sy) = (x - x0 as f32, y - y0 as f32);
+
+    let top = map[[y0, x0]] + (map[[y0, x1]] - map[[y0, x0]]) * sx;
+    let bottom = map[[y1, x0]] + (map[[y1, x1]] - map[[y1, x0]]) * sx;
+    top + (bottom - top) * sy
+}
+
+/// Resamples `map` to `new_width` × `new_height` with [`sample_bilinear`], lining up the
+/// centres of the corner pixels' areas rather than the pixels themselves, so the map isn't
+/// shifted.
+///
+/// Bilinear sampling only looks at the four nearest pixels, so shrinking by much more than
+/// half aliases; downsample in steps of at most 2 for smooth previews.
+pub fn resize(map: &Array2<f32>, new_width: usize, new_height: usize) -> Array2<f32> {
+    let (height, width) = map.dim();
+    let (scale_x, scale_y) = (width as f32 / new_width as f32, height as f32 / new_height as f32);
+
+    Array2::from_shape_fn([new_height, new_width], |(i, j)| {
+        sample_bilinear(map, (j as f32 + 0.5) * scale_x - 0.5, (i as f32 + 0.5) * scale_y - 0.5)
+    })
+}
+
+/// Halves `map` along both axes by averaging each 2 × 2 block of pixels, rounding odd sizes
+/// up; the last row or column of an odd-sized map averages just the pixels it covers.
+///
+/// Unlike [`resize`], every pixel counts equally towards the result, so the average height of
+/// the map is kept exactly when its sides are even.
+pub fn downsample(map: &Array2<f32>) -> Array2<f32> {
+    let (height, width) = map.dim();
+    Array2::from_shape_fn([height.div_ceil(2), width.div_ceil(2)], |(i, j)| {
+        let block = map.slice(s![2 * i..(2 * i + 2).min(height), 2 * j..(2 * j + 2).min(width)]);
+        block.sum() / block.len() as f32
+    })
+}
+
+/// A mip chain of `map`: `map` itself, then each level [`downsample`]d from the one before,
+/// down to a single pixel.
+pub fn mip_chain(map: &Array2<f32>) -> Vec<Array2<f32>> {
+    let mut chain = vec![map.clone()];
+    while let Some(last) = chain.last().filter(|level| level.len() > 1) {
+        chain.push(downsample(last));
+    }
+    chain
+}
@@ -0,0 +1,49 @@
+use std::fmt;
+
+use ndarray::Array2;
+
+use crate::post::min_max;
+
+/// Summary of a map's height distribution, for tuning octaves and normalization without
+/// loading the output into another tool.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct HeightmapStats {
+    pub min: f32,
+    pub max: f32,
+    pub mean: f32,
+    /// Population standard deviation.
+    pub std_dev: f32,
+    /// Numbers of pixels below and above the clamp range, which quantized output formats clamp.
+    /// The squircle falloff alone takes the corners of a default map below `-1`.
+    pub clipped: (usize, usize),
+}
+
+impl HeightmapStats {
+    /// Measures `map`, counting pixels outside the default clamp range `-1..=1`; an empty map
+    /// has all zero statistics.
+    pub fn of(map: &Array2<f32>) -> Self {
+        Self::with_range(map, (-1., 1.))
+    }
+
+    /// Measures `map`, counting pixels outside `lo..=hi`.
+    pub fn with_range(map: &Array2<f32>, (lo, hi): (f32, f32)) -> Self {
+        let (min, max) = min_max(map);
+        // summed in f64 so large maps don't lose precision
+        let n = map.len().max(1) as f64;
+        let (sum, sum_sq) = map.iter().fold((0f64, 0f64), |(s, sq), &v| {
+            let v = v as f64;
+            (s + v, sq + v * v)
+        });
+        let mean = sum / n;
+        let variance = (sum_sq / n - mean * mean).max(0.);
+        let clipped = (map.iter().filter(|&&v| v < lo).count(), map.iter().filter(|&&v| v > hi).count());
+        Self { min, max, mean: mean as f32, std_dev: variance.sqrt() as f32, clipped }
+    }
+}
+
+impl fmt::Display for HeightmapStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "min {:.4}, max {:.4}, mean {:.4}, std dev {:.4}, {} clipped low, {} clipped high", self.min,
+            self.max, self.mean, self.std_dev, self.clipped.0, self.clipped.1)
+    }
+}
@@ -0,0 +1,172 @@
+use ndarray::Array2;
+
+use crate::{ColorRamp, ConfigError, HeightmapConfig};
+
+/// A setting a [`contact_sheet`] varies.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SweepParam {
+    /// [`HeightmapConfig::octaves`], rounded to the nearest whole number.
+    Octaves,
+    Attenuation,
+    Lacunarity,
+    PerlinWeight,
+    WarpStrength,
+    Gain,
+}
+
+impl SweepParam {
+    pub const ALL: [SweepParam; 6] = [SweepParam::Octaves, SweepParam::Attenuation, SweepParam::Lacunarity,
+        SweepParam::PerlinWeight, SweepParam::WarpStrength, SweepParam::Gain];
+
+    /// The name the command line gives this setting, such as `perlin-weight`.
+    pub fn name(self) -> &'static str {
+        match self {
+            SweepParam::Octaves => "octaves",
+            SweepParam::Attenuation => "attenuation",
+            SweepParam::Lacunarity => "lacunarity",
+            SweepParam::PerlinWeight => "perlin-weight",
+            SweepParam::WarpStrength => "warp",
+            SweepParam::Gain => "gain",
+        }
+    }
+
+    /// The value of this setting in `config`.
+    pub fn get(self, config: &HeightmapConfig) -> f32 {
+        match self {
+            SweepParam::Octaves => config.octaves as f32,
+            SweepParam::Attenuation => config.attenuation,
+            SweepParam::Lacunarity => config.lacunarity,
+            SweepParam::PerlinWeight => config.perlin_weight,
+            SweepParam::WarpStrength => config.warp_strength,
+            SweepParam::Gain => config.gain,
+        }
+    }
+
+    /// `config` with this setting changed to `value`.
+    pub fn apply(self, config: &HeightmapConfig, value: f32) -> HeightmapConfig {
+        let mut config = config.clone();
+        match self {
+            SweepParam::Octaves => config.octaves = value.round().max(0.) as usize,
+            SweepParam::Attenuation => config.attenuation = value,
+            SweepParam::Lacunarity => config.lacunarity = value,
+            SweepParam::PerlinWeight => config.perlin_weight = value,
+            SweepParam::WarpStrength => config.warp_strength = value,
+            SweepParam::Gain => config.gain = value,
+        }
+        config
+    }
+}
+
+/// The values one axis of a [`contact_sheet`] sets a parameter to, one per column or row.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SweepAxis {
+    pub param: SweepParam,
+    pub values: Vec<f32>,
+}
+
+impl SweepAxis {
+    pub fn new(param: SweepParam, values: Vec<f32>) -> Self {
+        Self { param, values }
+    }
+
+    /// `steps` values evenly spaced from `from` to `to`, both included; a single step is `from`.
+    ///
+    /// Panics if `steps` is 0.
+    pub fn linspace(param: SweepParam, from: f32, to: f32, steps: usize) -> Self {
+        assert!(steps > 0, "a sweep needs at least one step");
+        let step = if steps > 1 { (to - from) / (steps - 1) as f32 } else { 0. };
+        Self::new(param, (0..steps).map(|k| from + step * k as f32).collect())
+    }
+}
+
+/// Pixels between thumbnails, and between them and the labels and edges.
+const GAP: usize = 4;
+/// How many pixels each pixel of a [`GLYPHS`] glyph is drawn as.
+const GLYPH_SCALE: usize = 2;
+/// 3 × 5 bitmaps of the characters labels are written with, one row of 3 bits per byte, top
+/// first; anything else is left blank.
+const GLYPHS: [(char, [u8; 5]); 12] = [
+    ('0', [0b111, 0b101, 0b101, 0b101, 0b111]),
+    ('1', [0b010, 0b110, 0b010, 0b010, 0b111]),
+    ('2', [0b111, 0b001, 0b111, 0b100, 0b111]),
+    ('3', [0b111, 0b001, 0b111, 0b001, 0b111]),
+    ('4', [0b101, 0b101, 0b111, 0b001, 0b001]),
+    ('5', [0b111, 0b100, 0b111, 0b001, 0b111]),
+    ('6', [0b111, 0b100, 0b111, 0b101, 0b111]),
+    ('7', [0b111, 0b001, 0b001, 0b001, 0b001]),
+    ('8', [0b111, 0b101, 0b111, 0b101, 0b111]),
+    ('9', [0b111, 0b101, 0b111, 0b001, 0b111]),
+    ('.', [0b000, 0b000, 0b000, 0b000, 0b010]),
+    ('-', [0b000, 0b000, 0b111, 0b000, 0b000]),
+];
+const BACKGROUND: [u8; 3] = [24, 24, 24];
+const INK: [u8; 3] = [224, 224, 224];
+
+/// A value as a label: to three decimals, without trailing zeros.
+fn label(value: f32) -> String {
+    let s = format!("{value:.3}");
+    let s = s.trim_end_matches('0').trim_end_matches('.');
+    if s == "-0" { "0".into() } else { s.into() }
+}
+
+/// Width in pixels of `text` drawn with [`draw_text`].
+fn text_width(text: &str) -> usize {
+    (text.chars().count() * 4).saturating_sub(1) * GLYPH_SCALE
+}
+
+/// Draws `text` with its top left corner at `(i, j)`, clipped to the image.
+fn draw_text(image: &mut Array2<[u8; 3]>, text: &str, (i, j): (usize, usize)) {
+    for (k, c) in text.chars().enumerate() {
+        let Some((_, rows)) = GLYPHS.iter().find(|&&(g, _)| g == c) else { continue };
+        for (y, row) in rows.iter().enumerate() { for x in 0..3 {
+            if row >> (2 - x) & 1 == 0 { continue; }
+            for dy in 0..GLYPH_SCALE { for dx in 0..GLYPH_SCALE {
+                let (pi, pj) = (i + y * GLYPH_SCALE + dy, j + (k * 4 + x) * GLYPH_SCALE + dx);
+                if let Some(p) = image.get_mut([pi, pj]) { *p = INK; }
+            }}
+        }}
+    }
+}
+
+/// A grid of thumbnails of `config`, with `columns` varying one parameter from left to right and
+/// `rows` another from top to bottom, each coloured with `ramp` and labelled with its value
+/// above the columns and left of the rows; where both axes set the same parameter the columns
+/// win. Each thumbnail is the [`preview`](HeightmapConfig::preview) of the changed configuration
+/// `thumb` pixels along its longer side, so even large maps make a sheet quickly. Labels show the
+/// values the settings end up with, so a swept octave count is labelled with whole numbers.
+///
+/// Fails with the first changed configuration that isn't valid, in row-major order.
+pub fn contact_sheet(config: &HeightmapConfig, columns: &SweepAxis, rows: &SweepAxis, thumb: usize,
+    ramp: &ColorRamp) -> Result<Array2<[u8; 3]>, ConfigError> {
+    let mut thumbs = Vec::with_capacity(columns.values.len() * rows.values.len());
+    for &r in &rows.values { for &c in &columns.values {
+        let cell = columns.param.apply(&rows.param.apply(config, r), c).preview(thumb);
+        cell.validate()?;
+        thumbs.push(ramp.apply(&cell.generate()));
+    }}
+
+    let (h, w) = thumbs.first().map_or((0, 0), |t| t.dim());
+    let label_height = 5 * GLYPH_SCALE;
+    // the value each axis actually set, after any rounding
+    let set = |axis: &SweepAxis, v: f32| label(axis.param.get(&axis.param.apply(config, v)));
+    let label_width = rows.values.iter().map(|&v| text_width(&set(rows, v))).max().unwrap_or(0);
+    let (top, left) = (label_height + GAP * 2, label_width + GAP * 2);
+    let (n_rows, n_cols) = (rows.values.len(), columns.values.len());
+    let mut sheet = Array2::from_elem([top + n_rows * (h + GAP), left + n_cols * (w + GAP)], BACKGROUND);
+
+    for (k, &c) in columns.values.iter().enumerate() {
+        draw_text(&mut sheet, &set(columns, c), (GAP, left + k * (w + GAP)));
+    }
+    for (k, &r) in rows.values.iter().enumerate() {
+        let text = set(rows, r);
+        let i = top + k * (h + GAP) + h.saturating_sub(label_height) / 2;
+        draw_text(&mut sheet, &text, (i, left - GAP - text_width(&text)));
+    }
+    for (k, thumb) in thumbs.iter().enumerate() {
+        let (i, j) = (top + k / n_cols * (h + GAP), left + k % n_cols * (w + GAP));
+        for ((y, x), &p) in thumb.indexed_iter() {
+            sheet[[i + y, j + x]] = p;
+        }
+    }
+    Ok(sheet)
+}
@@ -0,0 +1,106 @@
+use std::ops::Range;
+
+use ndarray::{s, Array2, Array3};
+use pcg_mwc::Mwc256XXA64;
+
+use crate::{
+    noise::{perlin3, random_gradient3, Point3},
+    par::for_each_outer,
+    post::{equalize, stretch},
+    HeightmapConfig, Normalization,
+};
+
+/// The gradient grid and sampling parameters of one octave of 3D noise.
+struct Octave3 {
+    vecs: Array3<Point3>,
+    /// Cell size along the depth, row and column axes.
+    cell: [f32; 3],
+    scale: f32,
+}
+
+fn octaves3(config: &HeightmapConfig, depth: usize) -> Vec<Octave3> {
+    let dims = [depth, config.height, config.width];
+    let mut rng = Mwc256XXA64::new(config.seed.0, config.seed.1);
+
+    let mut res = Vec::with_capacity(config.octaves);
+
+    for (level, scale) in config.levels() {
+        let cell_size = config.cell_size(level);
+        let mut cell = [cell_size, cell_size * config.feature_scale.1, cell_size * config.feature_scale.0];
+
+        // the depth axis wraps when the map wraps both ways
+        let wraps = [config.wrap_x && config.wrap_y, config.wrap_y, config.wrap_x];
+        let grid = [0, 1, 2].map(|a| {
+            let cells = dims[a] as f32 / cell[a];
+            if wraps[a] {
+                let cells = cells.round().max(1.);
+                cell[a] = dims[a] as f32 / cells;
+                cells as usize
+            } else {
+                cells.ceil() as usize + 1
+            }
+        });
+
+        let vecs = Array3::from_shape_simple_fn(grid, || random_gradient3(&mut rng));
+        res.push(Octave3 { vecs, cell, scale });
+    }
+
+    res
+}
+
+fn volume(config: &HeightmapConfig, depth: usize, slices: Range<usize>) -> Array3<f32> {
+    let octaves = octaves3(config, depth);
+    let scale_sum: f32 = octaves.iter().map(|o| o.scale).sum();
+
+    let mut res = Array3::from_elem([slices.len(), config.height, config.width], 0f32);
+
+    for_each_outer(&mut res, |k, mut slice| {
+        let z = (slices.start + k) as f32;
+        for ((i, j), v) in slice.indexed_iter_mut() {
+            let mut sum = 0f32;
+            for o in &octaves {
+                let n = perlin3(&o.vecs, z / o.cell[0], i as f32 / o.cell[1], j as f32 / o.cell[2], config.fade);
+                sum += config.style.shape(n) * o.scale;
+            }
+
+            *v = match config.normalization {
+                Normalization::ScaleSum => config.style.normalize(sum / scale_sum),
+                Normalization::MinMax | Normalization::None | Normalization::Equalize => sum,
+            };
+        }
+    });
+
+    match config.normalization {
+        Normalization::MinMax => stretch(&mut res),
+        Normalization::Equalize => equalize(&mut res),
+        Normalization::ScaleSum | Normalization::None => {}
+    }
+
+    res
+}
+
+impl HeightmapConfig {
+    /// Generates a `depth` × `height` × `width` block of 3D Perlin noise, indexed
+    /// `[slice, row, column]`, for caves, overhangs and other volumetric terrain.
+    ///
+    /// Octaves, attenuation, style, fade, normalization, feature scale and wrapping work as
+    /// for [`generate`](Self::generate), with cubic cells along the depth axis. The noise kind
+    /// and the 2D-only stages (anisotropy, octave rotation, squircle falloff, bump, island,
+    /// edge fade, terraces, warping, erosion and the region mask) are ignored. The depth axis
+    /// wraps if both `wrap_x` and `wrap_y` are set.
+    pub fn generate_volume(&self, depth: usize) -> Array3<f32> {
+        self.expect_valid();
+        volume(self, depth, 0..depth)
+    }
+
+    /// Slice `z` of [`generate_volume(depth)`](Self::generate_volume), without evaluating the
+    /// other slices. With [`Normalization::MinMax`] and [`Normalization::Equalize`] the slice is
+    /// remapped on its own.
+    ///
+    /// Panics if `z >= depth`.
+    pub fn generate_volume_slice(&self, depth: usize, z: usize) -> Array2<f32> {
+        assert!(z < depth, "slice {z} is outside a volume of depth {depth}");
+        self.expect_valid();
+        volume(self, depth, z..z + 1).slice_move(s![0, .., ..])
+    }
+}
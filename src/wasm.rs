@@ -0,0 +1,11 @@
+use wasm_bindgen::{prelude::wasm_bindgen, JsError};
+
+/// [`generate_png_bytes`](crate::generate_png_bytes) for JavaScript, which gets the PNG as a
+/// `Uint8Array`. The halves of the seed are passed as two `BigInt`s, since tuples can't be. An
+/// invalid configuration throws an `Error` with the [`ConfigError`](crate::ConfigError) message.
+#[wasm_bindgen(js_name = generate_png_bytes)]
+pub fn generate_png_bytes_js(width: usize, height: usize, octaves: usize, attenuation: f32, seed0: u64, seed1: u64)
+    -> Result<Vec<u8>, JsError> {
+    crate::generate_png_bytes(width, height, octaves, attenuation, (seed0, seed1))
+        .map_err(|e| JsError::new(&e.to_string()))
+}
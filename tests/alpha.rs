@@ -0,0 +1,32 @@
+#![cfg(feature = "std")]
+
+use heightmap_gen::{land_mask, write_png, write_png_alpha, write_rgba_png, BitDepth, ColorRamp, HeightmapConfig};
+
+#[test]
+fn alpha_follows_the_mask() {
+    let map = HeightmapConfig::default().with_size(40, 30).generate();
+    let mask = land_mask(&map, 0.);
+    assert!(mask.iter().any(|&m| m) && mask.iter().any(|&m| !m));
+
+    for depth in [BitDepth::Eight, BitDepth::Sixteen] {
+        let (mut plain, mut alpha) = (Vec::new(), Vec::new());
+        write_png(&mut plain, &map, depth, (-1., 1.)).unwrap();
+        write_png_alpha(&mut alpha, &map, depth, (-1., 1.), &mask).unwrap();
+        let (plain, alpha) = (image::load_from_memory(&plain).unwrap(), image::load_from_memory(&alpha).unwrap());
+        assert!(alpha.color().has_alpha());
+
+        // the gray samples are the same as without alpha
+        let (plain, alpha) = (plain.to_luma16(), alpha.to_luma_alpha16());
+        for ((l, la), &m) in plain.pixels().zip(alpha.pixels()).zip(mask.iter()) {
+            assert_eq!(l[0], la[0]);
+            assert_eq!(la[1], if m { u16::MAX } else { 0 });
+        }
+    }
+
+    let mut rgba = Vec::new();
+    write_rgba_png(&mut rgba, &ColorRamp::terrain().apply(&map), &mask).unwrap();
+    let rgba = image::load_from_memory(&rgba).unwrap().to_rgba8();
+    for (p, &m) in rgba.pixels().zip(mask.iter()) {
+        assert_eq!(p[3], if m { 255 } else { 0 });
+    }
+}
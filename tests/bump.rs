@@ -0,0 +1,39 @@
+#![cfg(feature = "std")]
+
+use heightmap_gen::{HeightmapConfig, Normalization};
+
+#[test]
+fn bump_rises_towards_its_centre() {
+    let config = HeightmapConfig::default().with_size(64, 48).with_perlin_weight(1.);
+    let map = config.generate();
+    let bumped = config.clone().with_bump(Some((0.5, 2., (0.25, 0.5)))).generate();
+
+    for ((i, j), &v) in bumped.indexed_iter() {
+        let d = ((i as f32 - 24.) / 24.).hypot((j as f32 - 16.) / 32.);
+        let lift = 0.5 * (1. - d).max(0.).powi(2);
+        assert!((v - map[[i, j]] - lift).abs() < 1e-5, "pixel ({i}, {j})");
+    }
+    // the peak is the full amplitude, and the far side of the map is left alone
+    assert!((bumped[[24, 16]] - map[[24, 16]] - 0.5).abs() < 1e-6);
+    assert_eq!(bumped[[24, 63]], map[[24, 63]]);
+}
+
+#[test]
+fn bump_comes_before_normalization() {
+    let config = HeightmapConfig::default().with_size(64, 64).with_perlin_weight(1.)
+        .with_normalization(Normalization::MinMax).with_bump(Some((4., 1., (0.5, 0.5))));
+    let map = config.generate();
+    // stretched back to the full range, with a bump this tall peaking in the middle
+    let (lo, hi) = map.iter().fold((f32::MAX, f32::MIN), |(lo, hi), &v| (lo.min(v), hi.max(v)));
+    assert!((lo + 1.).abs() < 1e-5 && (hi - 1.).abs() < 1e-5);
+    let ((i, j), _) = map.indexed_iter().max_by(|a, b| a.1.total_cmp(b.1)).unwrap();
+    assert!((i as f32 - 32.).hypot(j as f32 - 32.) < 8., "peak at ({i}, {j})");
+}
+
+#[test]
+fn strips_include_the_bump() {
+    let config = HeightmapConfig::default().with_size(40, 30).with_bump(Some((-0.6, 0.5, (0.8, 0.2))));
+    let map = config.generate();
+    let rows: Vec<f32> = config.generate_strips(7).flat_map(|s| s.into_iter()).collect();
+    assert_eq!(rows, map.into_iter().collect::<Vec<_>>());
+}
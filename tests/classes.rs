@@ -0,0 +1,44 @@
+#![cfg(feature = "std")]
+
+use heightmap_gen::{classify, write_indexed_png, HeightmapConfig, TERRAIN_BANDS, TERRAIN_PALETTE};
+use ndarray::{array, Array2};
+
+#[test]
+fn pixels_take_the_highest_band_at_or_below_them() {
+    let map = array![[-2., -1., -0.5, 0.], [0.2, 0.5, 0.99, 3.]];
+    // in any order, and the lowest band covers everything below it too
+    let classes = classify(&map, &[(0.5, 7), (-1., 1), (0., 3)]);
+    assert_eq!(classes, array![[1, 1, 1, 3], [3, 7, 7, 7]]);
+
+    assert!(classify(&map, &[(0., 4)]).iter().all(|&c| c == 4));
+}
+
+#[test]
+fn terrain_bands_puts_the_shoreline_at_zero() {
+    let map = array![[-0.9, -0.1, 0.01, 0.1, 0.3, 0.6, 0.95]];
+    assert_eq!(classify(&map, &TERRAIN_BANDS), array![[0, 1, 2, 3, 4, 5, 6]]);
+}
+
+#[test]
+fn indexed_pngs_keep_the_class_ids_and_palette() {
+    let map = HeightmapConfig::default().with_size(40, 30).generate();
+    let classes = classify(&map, &TERRAIN_BANDS);
+    let mut buf = Vec::new();
+    write_indexed_png(&mut buf, &classes, &TERRAIN_PALETTE).unwrap();
+
+    let mut decoder = png::Decoder::new(buf.as_slice());
+    decoder.set_transformations(png::Transformations::IDENTITY);
+    let mut reader = decoder.read_info().unwrap();
+    assert_eq!(reader.info().color_type, png::ColorType::Indexed);
+    assert_eq!(reader.info().palette.as_deref(), Some(TERRAIN_PALETTE.concat().as_slice()));
+    let mut ids = vec![0; reader.output_buffer_size()];
+    let frame = reader.next_frame(&mut ids).unwrap();
+    assert_eq!((frame.width, frame.height), (40, 30));
+    assert_eq!(Array2::from_shape_vec([30, 40], ids).unwrap(), classes);
+}
+
+#[test]
+#[should_panic(expected = "every class needs a colour")]
+fn classes_outside_the_palette_panic() {
+    write_indexed_png(Vec::new(), &array![[0, 2]], &[[0, 0, 0], [255, 255, 255]]).unwrap();
+}
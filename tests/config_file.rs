@@ -0,0 +1,46 @@
+#![cfg(feature = "config-file")]
+
+use heightmap_gen::{
+    Curve, HeightmapConfig, HydraulicParams, Invert, NoiseKind, NoiseStyle, Normalization, WorleyMode,
+};
+
+#[test]
+fn toml_sets_fields_and_keeps_defaults() {
+    let config: HeightmapConfig = toml::from_str(r#"
+        width = 96
+        height = 64
+        seed = [7, "0xFFFFFFFFFFFFFFFF"]
+        noise = { worley = "f2-minus-f1" }
+        style = { ridged = { squared = true } }
+        normalization = "min-max"
+        terraces = [6, 0.25]
+        hydraulic_erosion = [500, { inertia = 0.1 }]
+        curve = { points = [[0.0, 0.0], [0.5, 0.25], [1.0, 1.0]] }
+    "#).unwrap();
+
+    let expected = HeightmapConfig::default()
+        .with_size(96, 64)
+        .with_seed((7, u64::MAX))
+        .with_noise(NoiseKind::Worley(WorleyMode::F2MinusF1))
+        .with_style(NoiseStyle::Ridged { squared: true })
+        .with_normalization(Normalization::MinMax)
+        .with_terraces(6, 0.25)
+        .with_hydraulic_erosion(500, HydraulicParams { inertia: 0.1, ..HydraulicParams::default() })
+        .with_curve(Some(Curve::Points(vec![(0., 0.), (0.5, 0.25), (1., 1.)])));
+    assert_eq!(config, expected);
+}
+
+#[test]
+fn json_reads_the_same_fields() {
+    let config: HeightmapConfig = serde_json::from_str(r#"{ "octaves": 5, "invert": "before-island" }"#).unwrap();
+    assert_eq!(config, HeightmapConfig::default().with_octaves(5).with_invert(Some(Invert::BeforeIsland)));
+}
+
+#[test]
+fn unknown_fields_are_errors() {
+    let err = toml::from_str::<HeightmapConfig>("octaves = 4\nocatves = 5").unwrap_err();
+    assert!(err.to_string().contains("ocatves"), "{err}");
+    let err = toml::from_str::<HeightmapConfig>("hydraulic_erosion = [5, { inertai = 0.1 }]").unwrap_err();
+    assert!(err.to_string().contains("inertai"), "{err}");
+    assert!(toml::from_str::<HeightmapConfig>("normalization = \"minmax\"").is_err());
+}
@@ -0,0 +1,41 @@
+#![cfg(feature = "std")]
+
+use heightmap_gen::{Craters, HeightmapConfig, Pipeline};
+use ndarray::Array2;
+
+/// Crater floors: pixels lower than all eight neighbours by the carving alone.
+fn count_craters(plain: &Array2<f32>, cratered: &Array2<f32>) -> usize {
+    let carved = cratered - plain;
+    let (height, width) = carved.dim();
+    let mut count = 0;
+    for i in 1..height - 1 { for j in 1..width - 1 {
+        let v = carved[[i, j]];
+        let lowest = (i - 1..=i + 1).all(|y| (j - 1..=j + 1).all(|x| (y, x) == (i, j) || carved[[y, x]] > v));
+        if lowest && v < -0.2 { count += 1; }
+    }}
+    count
+}
+
+#[test]
+fn crater_count_follows_the_cell_size() {
+    let config = HeightmapConfig::default().with_size(256, 256);
+    let plain = config.generate();
+    let counts = [32., 16.].map(|cell_size| {
+        let craters = Craters { cell_size, depth: 0.5, ..Craters::default() };
+        count_craters(&plain, &Pipeline::cratered(craters).run(&config).unwrap())
+    });
+    // about one crater per cell, minus some at the edges
+    assert!((48..=72).contains(&counts[0]), "{counts:?}");
+    let ratio = counts[1] as f32 / counts[0] as f32;
+    assert!((3. ..=5.).contains(&ratio), "{counts:?}");
+}
+
+#[test]
+fn cells_must_have_a_size() {
+    let config = HeightmapConfig::default().with_size(32, 32);
+    let craters = Craters { cell_size: 0., ..Craters::default() };
+    let e = Pipeline::cratered(craters).run(&config).unwrap_err();
+    assert!(e.to_string().starts_with("can't carve craters: feature scale"), "{e}");
+    let tiny = Craters { cell_size: 1e-6, ..Craters::default() };
+    assert!(Pipeline::cratered(tiny).run(&config).is_err());
+}
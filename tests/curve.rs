@@ -0,0 +1,61 @@
+#![cfg(feature = "std")]
+
+use heightmap_gen::{ConfigError, Curve, HeightmapConfig};
+
+#[test]
+fn gamma_of_one_is_identity() {
+    let config = HeightmapConfig::default().with_size(64, 48);
+    assert_eq!(config.clone().with_curve(Some(Curve::Gamma(1.))).generate(), config.generate());
+}
+
+#[test]
+fn curves_reshape_heights_within_the_clamp_range() {
+    let config = HeightmapConfig::default().with_size(64, 48).with_clamp_range(-1., 0.5);
+    let map = config.generate();
+
+    let gamma = config.clone().with_curve(Some(Curve::Gamma(2.))).generate();
+    for (&v, &g) in map.iter().zip(&gamma) {
+        let t = (v + 1.) / 1.5;
+        let expected = if (0. ..=1.).contains(&t) { t * t * 1.5 - 1. } else { v };
+        assert!((g - expected).abs() < 1e-5);
+    }
+    assert!(gamma.mean().unwrap() < map.mean().unwrap());
+
+    // squashes the lower half into the bottom quarter and stretches the upper half over the rest
+    let points = config.with_curve(Some(Curve::Points(vec![(0., 0.), (0.5, 0.25), (1., 1.)]))).generate();
+    for (&v, &p) in map.iter().zip(&points) {
+        let t = (v + 1.) / 1.5;
+        let expected = if !(0. ..=1.).contains(&t) {
+            v
+        } else if t < 0.5 {
+            t * 0.5 * 1.5 - 1.
+        } else {
+            (0.25 + (t - 0.5) * 1.5) * 1.5 - 1.
+        };
+        assert!((p - expected).abs() < 1e-5);
+    }
+}
+
+#[test]
+fn gradients_follow_the_curve() {
+    let config = HeightmapConfig::default().with_size(64, 48).with_curve(Some(Curve::Gamma(1.5)));
+    let (map, gradient) = config.generate_with_gradient();
+    assert_eq!(map, config.generate());
+    // central differences against the analytic gradient, away from where the curve bends
+    for i in (4..44).step_by(7) { for j in (4..60).step_by(7) {
+        let (di, dj) = gradient[[i, j]];
+        let fi = (map[[i + 1, j]] - map[[i - 1, j]]) / 2.;
+        let fj = (map[[i, j + 1]] - map[[i, j - 1]]) / 2.;
+        assert!((di - fi).abs() < 0.01 && (dj - fj).abs() < 0.01, "{:?} vs {:?}", (di, dj), (fi, fj));
+    }}
+}
+
+#[test]
+fn rejects_unusable_curves() {
+    let base = HeightmapConfig::default().with_size(16, 16);
+    for curve in [Curve::Gamma(0.), Curve::Gamma(f32::NAN), Curve::Points(vec![]),
+        Curve::Points(vec![(0.5, 0.), (0.5, 1.)])] {
+        // matched rather than compared, since a NaN gamma never equals itself
+        assert!(matches!(base.clone().with_curve(Some(curve)).validate(), Err(ConfigError::Curve(_))));
+    }
+}
@@ -0,0 +1,32 @@
+#![cfg(feature = "std")]
+
+//! Locks the generator's output down, so changes that alter the maps a seed produces are made
+//! on purpose. See the notes on [`HeightmapConfig::seed`] for what can still differ between
+//! platforms.
+
+use heightmap_gen::{GradientSource, HeightmapConfig};
+
+/// FNV-1a over the little-endian bytes of every height, in row-major order.
+fn hash(config: &HeightmapConfig) -> u64 {
+    config.generate().iter().flat_map(|v| v.to_le_bytes()).fold(0xCBF29CE484222325, |h, b| {
+        (h ^ b as u64).wrapping_mul(0x100000001B3)
+    })
+}
+
+#[test]
+fn random_angles_reference() {
+    // the default before hashed gradients, which old maps have to keep reproducing
+    let config =
+        HeightmapConfig::default().with_size(64, 64).with_octaves(4).with_gradients(GradientSource::RandomAngles);
+    // summing in f64 rounds differently
+    let expected = if cfg!(feature = "f64") { 0xA763DBDFEE4B9DC4 } else { 0xA0B16E0D30519818 };
+    assert_eq!(hash(&config), expected, "generated map changed for {config:?}");
+}
+
+#[test]
+fn default_seed_reference() {
+    let config = HeightmapConfig::default().with_size(64, 64).with_octaves(4);
+    // summing in f64 rounds differently
+    let expected = if cfg!(feature = "f64") { 0x6D9B21E8010E2A96 } else { 0x30C7D2A2B3537DB6 };
+    assert_eq!(hash(&config), expected, "generated map changed for {config:?}");
+}
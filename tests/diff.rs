@@ -0,0 +1,13 @@
+#![cfg(feature = "std")]
+
+use heightmap_gen::difference_image;
+use ndarray::arr2;
+
+#[test]
+fn differences_are_centred_on_mid_gray() {
+    let a = arr2(&[[0., 0.5], [-1., 1.]]);
+    let b = arr2(&[[0., 0.25], [1., -1.]]);
+    assert_eq!(difference_image(&a, &b, false, 1.), arr2(&[[0.5, 0.4375], [1., 0.]]));
+    assert_eq!(difference_image(&a, &b, true, 1.), arr2(&[[0., 0.125], [1., 1.]]));
+    assert_eq!(difference_image(&a, &b, true, 4.), arr2(&[[0., 0.5], [4., 4.]]));
+}
@@ -0,0 +1,32 @@
+#![cfg(feature = "std")]
+
+use heightmap_gen::HeightmapConfig;
+
+#[test]
+fn edges_fade_to_the_target() {
+    let config = HeightmapConfig::default().with_size(64, 48);
+    let map = config.generate();
+    let faded = config.clone().with_edge_fade(Some((8, -0.5))).generate();
+
+    for ((i, j), &v) in faded.indexed_iter() {
+        let edge = i.min(47 - i).min(j).min(63 - j);
+        if edge == 0 {
+            assert_eq!(v, -0.5);
+        } else if edge >= 8 {
+            assert_eq!(v, map[[i, j]]);
+        } else {
+            // strictly between the target and the unfaded height
+            let (lo, hi) = (map[[i, j]].min(-0.5), map[[i, j]].max(-0.5));
+            assert!(lo <= v && v <= hi, "pixel ({i}, {j})");
+        }
+    }
+    // a half cosine: halfway through the margin, halfway to the target
+    let (i, j) = (4, 32);
+    assert!((faded[[i, j]] - (map[[i, j]] - 0.5) / 2.).abs() < 1e-5);
+
+    // edges that wrap aren't faded
+    let wrapping = config.with_wrap(true, false);
+    let (map, faded) = (wrapping.generate(), wrapping.clone().with_edge_fade(Some((8, -0.5))).generate());
+    assert_eq!(faded[[24, 0]], map[[24, 0]]);
+    assert_eq!(faded[[0, 32]], -0.5);
+}
@@ -0,0 +1,39 @@
+#![cfg(feature = "std")]
+
+use std::io::Cursor;
+
+use exr::prelude::{read, FlatSamples, ReadChannels, ReadLayers};
+use heightmap_gen::{write_exr, HeightmapConfig};
+use ndarray::Array2;
+
+/// The size and samples of the only channel of an EXR image.
+fn read_back(bytes: Vec<u8>) -> (usize, usize, Vec<f32>) {
+    let image = read().no_deep_data().largest_resolution_level().all_channels().first_valid_layer().all_attributes()
+        .from_buffered(Cursor::new(bytes))
+        .unwrap();
+    let layer = image.layer_data;
+    assert_eq!(layer.channel_data.list.len(), 1);
+    assert!(layer.channel_data.list[0].name == *"Y");
+    match &layer.channel_data.list[0].sample_data {
+        FlatSamples::F32(samples) => (layer.size.0, layer.size.1, samples.clone()),
+        other => panic!("expected f32 samples, got {other:?}"),
+    }
+}
+
+#[test]
+fn exr_round_trip() {
+    let map = HeightmapConfig::default().with_size(70, 45).with_gain_bias(1.8, 0.).generate();
+    assert!(map.iter().any(|v| v.abs() > 1.));
+
+    let mut bytes = Vec::new();
+    write_exr(&mut bytes, &map, None).unwrap();
+    let (width, height, samples) = read_back(bytes);
+    assert_eq!((width, height), (70, 45));
+    let back = Array2::from_shape_vec((height, width), samples).unwrap();
+    assert!(back.iter().zip(&map).all(|(a, b)| a.to_bits() == b.to_bits()));
+
+    let mut bytes = Vec::new();
+    write_exr(&mut bytes, &map, Some((-1., 1.))).unwrap();
+    let (.., samples) = read_back(bytes);
+    assert!(samples.iter().zip(&map).all(|(&a, b)| a == b.clamp(-1., 1.)));
+}
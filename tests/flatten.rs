@@ -0,0 +1,30 @@
+#![cfg(feature = "std")]
+
+use heightmap_gen::{HeightmapConfig, Pipeline, Stage};
+
+#[test]
+fn flattens_past_the_thresholds() {
+    let config = HeightmapConfig::default().with_size(64, 48);
+    let map = config.generate();
+    let flatten = |below, above, band| {
+        Pipeline::new().then(Stage::Flatten { below, above, band }).run(&config).unwrap()
+    };
+    let sharp = flatten(Some(-0.2), Some(0.4), 0.);
+    for (&v, &f) in map.iter().zip(&sharp) {
+        assert_eq!(f, v.clamp(-0.2, 0.4));
+    }
+    assert!(sharp.iter().filter(|&&v| v == -0.2).count() > 100);
+
+    let band = 0.2;
+    let smooth = flatten(Some(-0.2), None, band);
+    for (&v, &f) in map.iter().zip(&smooth) {
+        if v <= -0.2 - band / 2. {
+            assert_eq!(f, -0.2);
+        } else if v >= -0.2 + band / 2. {
+            assert_eq!(f, v);
+        } else {
+            // rounded off: above both the height and the level, by at most a quarter band
+            assert!(f >= v.max(-0.2) && f <= v.max(-0.2) + band / 4. + 1e-6, "{v} -> {f}");
+        }
+    }
+}
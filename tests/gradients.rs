@@ -0,0 +1,43 @@
+#![cfg(feature = "std")]
+
+use heightmap_gen::{GradientGrids, GradientSource, GradientsError, HeightmapConfig, NoiseKind, WorleyMode};
+
+fn round_trip(grids: &GradientGrids) -> GradientGrids {
+    let mut file = Vec::new();
+    grids.write(&mut file).unwrap();
+    GradientGrids::read(&file[..]).unwrap()
+}
+
+#[test]
+fn saved_grids_reproduce_the_map() {
+    let base = HeightmapConfig::default().with_size(50, 40);
+    let configs = [
+        base.clone().with_gradients(GradientSource::RandomAngles).with_warp_strength(6.),
+        base.clone().with_gradients(GradientSource::Table).with_tileable(true),
+        base.clone().with_noise(NoiseKind::Worley(WorleyMode::F1)).with_finest_level(2),
+    ];
+    for config in configs {
+        let grids = round_trip(&config.gradient_grids());
+        assert_eq!(grids, config.gradient_grids());
+        assert_eq!(config.generate_from_grids(&grids).unwrap(), config.generate(), "{config:?}");
+    }
+}
+
+#[test]
+fn rejects_other_files_and_configurations() {
+    let config = HeightmapConfig::default().with_size(50, 40);
+    let mut file = Vec::new();
+    config.gradient_grids().write(&mut file).unwrap();
+
+    let mut newer = file.clone();
+    newer[8] = 2;
+    assert!(matches!(GradientGrids::read(&newer[..]), Err(GradientsError::Version(2))));
+    assert!(matches!(GradientGrids::read(&b"P5\n50 40\n"[..]), Err(GradientsError::NotGradients)));
+    assert!(matches!(GradientGrids::read(&file[..file.len() - 1]), Err(GradientsError::Io(_))));
+
+    let grids = GradientGrids::read(&file[..]).unwrap();
+    let fewer = config.clone().with_octaves(7);
+    assert!(matches!(fewer.generate_from_grids(&grids), Err(GradientsError::GridCount { expected: 7, found: 8 })));
+    let wider = config.with_size(80, 40);
+    assert!(matches!(wider.generate_from_grids(&grids), Err(GradientsError::GridSize { .. })));
+}
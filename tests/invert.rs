@@ -0,0 +1,19 @@
+#![cfg(feature = "std")]
+
+use heightmap_gen::{HeightmapConfig, Invert};
+
+#[test]
+fn inverting_flips_within_the_clamp_range() {
+    let config = HeightmapConfig::default().with_size(96, 64).with_island(Some(2.)).with_clamp_range(-1., 0.5);
+    let map = config.generate();
+
+    let inverted = config.clone().with_invert(Some(Invert::AfterIsland)).generate();
+    for (a, b) in map.iter().zip(&inverted) {
+        assert!((a + b + 0.5).abs() < 1e-6);
+    }
+
+    // inverting before the falloff still sinks the corners
+    let basins = config.with_invert(Some(Invert::BeforeIsland)).generate();
+    assert_eq!(basins[[0, 0]], map[[0, 0]]);
+    assert!(basins[[32, 48]] != map[[32, 48]]);
+}
@@ -0,0 +1,63 @@
+use std::f32::consts::FRAC_1_SQRT_2;
+
+#[cfg(feature = "std")]
+use heightmap_gen::{HeightmapConfig, NoiseStyle, WorleyMode};
+use heightmap_gen::{Fade, Fbm, LatticeSlice, NoiseKind};
+
+#[test]
+#[cfg(feature = "std")]
+fn fbm_matches_the_generator() {
+    for (noise, style) in [
+        (NoiseKind::Perlin, NoiseStyle::Fbm),
+        (NoiseKind::Simplex, NoiseStyle::Billow),
+        (NoiseKind::Worley(WorleyMode::F1), NoiseStyle::Ridged { squared: true }),
+    ] {
+        let fbm = Fbm::default().with_octaves(6).with_noise(noise).with_style(style).with_seed((7, 11));
+        let mut map = vec![0.; 48 * 40];
+        fbm.fill(&mut map, 48);
+
+        let config = HeightmapConfig::default().with_size(48, 40).with_perlin_weight(1.).with_octaves(6)
+            .with_noise(noise).with_style(style).with_seed((7, 11));
+        for (&a, &b) in map.iter().zip(config.generate().iter()) {
+            assert!((a - b).abs() < 1e-5, "{noise:?}: {a} vs {b}");
+        }
+    }
+}
+
+#[test]
+#[should_panic(expected = "at least one octave")]
+fn fbm_needs_an_octave() {
+    Fbm::default().with_octaves(0).sample(3., 4.);
+}
+
+#[test]
+fn lattice_slices_wrap_around() {
+    let points = [(1., 0.), (0., 1.), (-1., 0.), (0., -1.), (0.6, 0.8), (-0.8, 0.6)];
+    let lattice = LatticeSlice::new(&points, 3);
+    for (x, y) in [(0.25, 0.75), (1.25, 0.5), (2.875, 1.125)] {
+        let v = lattice.sample(NoiseKind::Perlin, x, y, Fade::Quintic);
+        assert_eq!(v, lattice.sample(NoiseKind::Perlin, x + 3., y + 2., Fade::Quintic));
+        assert!(v != 0. && v.abs() <= 1.);
+    }
+    // lattice points are where gradient noise is 0
+    assert_eq!(lattice.sample(NoiseKind::Perlin, 2., 1., Fade::Cubic), 0.);
+}
+
+#[test]
+#[should_panic(expected = "whole rows")]
+fn ragged_lattices_panic() {
+    LatticeSlice::new(&[(1., 0.); 5], 3);
+}
+
+#[test]
+fn value_noise_eases_between_lattice_values() {
+    let points = [(0.5, 0.), (-1., 0.), (0.25, 0.), (1., 0.)];
+    let lattice = LatticeSlice::new(&points, 2);
+    let value = |x, y, fade| lattice.sample(NoiseKind::Value, x, y, fade) / FRAC_1_SQRT_2;
+    assert!((value(0., 0., Fade::Cubic) - 0.5).abs() < 1e-6);
+    assert!((value(1., 1., Fade::Cubic) - 1.).abs() < 1e-6);
+    assert!((value(0.5, 0., Fade::Cubic) + 0.25).abs() < 1e-6);
+    assert!((value(0.25, 0.5, Fade::Linear) - 0.28125).abs() < 1e-6);
+    // smoothstep pulls a quarter of the way across towards the nearer point
+    assert!(value(0.25, 0., Fade::Cubic) > value(0.25, 0., Fade::Linear));
+}
@@ -0,0 +1,56 @@
+#![cfg(feature = "std")]
+
+use heightmap_gen::{ConfigError, GradientSource, HeightmapConfig, Normalization};
+
+#[test]
+fn bands_add_up_to_the_full_map() {
+    for gradients in [GradientSource::Hashed, GradientSource::RandomAngles, GradientSource::Table] {
+        let full = HeightmapConfig::default().with_size(64, 48).with_octaves(6)
+            .with_perlin_weight(1.).with_gradients(gradients).with_normalization(Normalization::None);
+        let coarse = full.clone().with_finest_level(3).generate();
+        let detail = full.clone().with_coarsest_level(Some(2)).generate();
+        let full = full.generate();
+        for ((i, j), &v) in full.indexed_iter() {
+            let sum = coarse[[i, j]] + detail[[i, j]];
+            assert!((v - sum).abs() < 1e-5, "pixel ({i}, {j}) with {gradients:?}: {v} vs {sum}");
+        }
+    }
+}
+
+#[test]
+fn rejects_an_empty_band() {
+    let config = HeightmapConfig::default().with_finest_level(3).with_coarsest_level(Some(2));
+    assert_eq!(config.validate(), Err(ConfigError::LevelRange { finest_level: 3, coarsest_level: 2 }));
+}
+
+#[test]
+fn detail_cap_skips_fine_levels() {
+    let config = HeightmapConfig::default().with_size(64, 48);
+    let capped = config.clone().with_detail_cap(Some(4.)).generate();
+    assert_eq!(capped, config.clone().with_finest_level(2).generate());
+
+    // cells are measured along their narrower side once the feature scale stretches them
+    let stretched = config.clone().with_feature_scale(2., 3.);
+    assert_eq!(stretched.clone().with_detail_cap(Some(4.)).generate(), stretched.with_finest_level(1).generate());
+
+    let config = config.with_octaves(4);
+    assert_eq!(config.clone().with_detail_cap(Some(16.)).validate(), Err(ConfigError::DetailCap(16.)));
+    assert_eq!(config.with_detail_cap(Some(8.)).with_coarsest_level(Some(2)).validate(),
+        Err(ConfigError::LevelRange { finest_level: 3, coarsest_level: 2 }));
+}
+
+#[test]
+fn octave_seeds_keep_gradients_when_octaves_change() {
+    for gradients in [GradientSource::RandomAngles, GradientSource::Table] {
+        let config = HeightmapConfig::default().with_size(64, 48).with_gradients(gradients);
+        // the six finest of eight octaves, against six octaves
+        let eight =
+            config.clone().with_octaves(8).with_coarsest_level(Some(5)).with_octave_weights(Some(vec![1.; 8]));
+        let six = config.with_octaves(6).with_octave_weights(Some(vec![1.; 6]));
+        assert_ne!(eight.generate(), six.generate());
+
+        let (eight, six) = (eight.with_octave_seeds(true), six.with_octave_seeds(true));
+        assert_eq!(eight.generate(), six.generate(), "{gradients:?}");
+        assert_ne!(six.generate(), six.clone().with_seed((1, 2)).generate());
+    }
+}
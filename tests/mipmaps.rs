@@ -0,0 +1,26 @@
+#![cfg(feature = "std")]
+
+use heightmap_gen::{downsample, mip_chain, HeightmapConfig};
+
+#[test]
+fn each_level_averages_the_one_before() {
+    let map = HeightmapConfig::default().with_size(37, 20).generate();
+    let chain = mip_chain(&map);
+    let dims: Vec<_> = chain.iter().map(|level| level.dim()).collect();
+    assert_eq!(dims, [(20, 37), (10, 19), (5, 10), (3, 5), (2, 3), (1, 2), (1, 1)]);
+    assert_eq!(chain[0], map);
+
+    for pair in chain.windows(2) {
+        let (fine, coarse) = (&pair[0], &pair[1]);
+        assert_eq!(&downsample(fine), coarse);
+        for ((i, j), &v) in coarse.indexed_iter() {
+            // every finer pixel the coarse one covers, clipped at odd edges
+            let mut block = Vec::new();
+            for y in 2 * i..(2 * i + 2).min(fine.nrows()) { for x in 2 * j..(2 * j + 2).min(fine.ncols()) {
+                block.push(fine[[y, x]]);
+            }}
+            let mean = block.iter().sum::<f32>() / block.len() as f32;
+            assert!((v - mean).abs() < 1e-6, "pixel ({i}, {j})");
+        }
+    }
+}
@@ -0,0 +1,43 @@
+#![cfg(feature = "std")]
+
+use heightmap_gen::{ambient_occlusion, HeightmapConfig};
+use ndarray::{s, Array2};
+
+#[test]
+fn horizons_shade_valleys() {
+    let flat = ambient_occlusion(&Array2::from_elem([16, 16], -0.2), 8, 4., (false, false));
+    assert!(flat.iter().all(|&o| (o - 1.).abs() < 1e-6));
+
+    // a valley running down the middle, between walls rising 1 over 8 pixels
+    let valley = Array2::from_shape_fn([32, 32], |(_, j)| ((j as f32 - 16.).abs() / 8.).min(1.));
+    let occlusion = ambient_occlusion(&valley, 8, 8., (false, false));
+    assert!(occlusion.iter().all(|o| (0. ..=1.).contains(o)));
+    assert!(occlusion[[16, 16]] < 0.8);
+    assert!(occlusion[[16, 16]] < occlusion[[16, 20]] && occlusion[[16, 20]] < occlusion[[16, 28]]);
+    assert_eq!(occlusion[[16, 30]], 1.);
+}
+
+#[test]
+fn wrapped_horizons_tile() {
+    let map = HeightmapConfig::default().with_size(32, 32).with_wrap(true, true).generate();
+    let occlusion = ambient_occlusion(&map, 8, 6., (true, true));
+
+    // moving the seam to the middle of the map moves the occlusion with it
+    let mut shifted = map.clone();
+    shifted.slice_mut(s![.., ..16]).assign(&map.slice(s![.., 16..]));
+    shifted.slice_mut(s![.., 16..]).assign(&map.slice(s![.., ..16]));
+    let moved = ambient_occlusion(&shifted, 8, 6., (true, true));
+    for ((i, j), &o) in moved.indexed_iter() {
+        assert!((o - occlusion[[i, (j + 16) % 32]]).abs() < 1e-5, "{i} {j}");
+    }
+
+    // clamped at the edges, the seam shows
+    let clamped = ambient_occlusion(&shifted, 8, 6., (false, false));
+    assert!(clamped.iter().zip(&moved).any(|(a, b)| (a - b).abs() > 1e-3));
+}
+
+#[test]
+#[should_panic(expected = "at least one direction")]
+fn needs_a_direction() {
+    ambient_occlusion(&Array2::zeros([4, 4]), 0, 2., (false, false));
+}
@@ -0,0 +1,33 @@
+#![cfg(feature = "std")]
+
+use heightmap_gen::{blur_occlusion, packed_map, slope_map, HeightmapConfig};
+use ndarray::Array2;
+
+#[test]
+fn channels_hold_height_slope_and_occlusion() {
+    let config = HeightmapConfig::default().with_size(48, 40);
+    let map = config.generate();
+    let packed = packed_map(&map, config.clamp_range, 4., (false, false));
+    let (slopes, occlusion) = (slope_map(&map, (false, false)), blur_occlusion(&map, 4.));
+
+    let byte = |v: f32| (v * 255.).round() as u8;
+    for (ij, &[r, g, b]) in packed.indexed_iter() {
+        assert_eq!(r, byte((map[ij].clamp(-1., 1.) + 1.) / 2.));
+        assert_eq!((g, b), (byte(slopes[ij]), byte(occlusion[ij])));
+    }
+    assert!(packed.iter().any(|p| p[2] == 0) && packed.iter().any(|p| p[2] == 255));
+}
+
+#[test]
+fn pits_are_occluded_and_flat_ground_is_not() {
+    assert!(blur_occlusion(&Array2::from_elem([16, 16], 0.3), 3.).iter().all(|&o| o == 1.));
+
+    // a bowl sinking towards the middle
+    let bowl = Array2::from_shape_fn([33, 33], |(i, j)| {
+        let (y, x) = (i as f32 - 16., j as f32 - 16.);
+        (x * x + y * y).sqrt() / 16.
+    });
+    let occlusion = blur_occlusion(&bowl, 3.);
+    assert_eq!(occlusion[[16, 16]], 0.);
+    assert!(occlusion[[16, 16]] < occlusion[[16, 8]] && occlusion[[16, 8]] <= 1.);
+}
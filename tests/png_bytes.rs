@@ -0,0 +1,23 @@
+#![cfg(feature = "std")]
+
+use std::io::Cursor;
+
+use heightmap_gen::{generate_heightmap, generate_png_bytes, read_png, write_png, BitDepth, ConfigError};
+
+#[test]
+fn bytes_are_the_png_write_png_writes() {
+    let seed = (3, 5);
+    let bytes = generate_png_bytes(40, 24, 6, 0.5, seed).unwrap();
+    let mut expected = Vec::new();
+    write_png(&mut expected, &generate_heightmap(40, 24, 6, 0.5, seed), BitDepth::Eight, (-1., 1.)).unwrap();
+    assert_eq!(bytes, expected);
+    assert_eq!(read_png(Cursor::new(bytes)).unwrap().dim(), (24, 40));
+}
+
+#[test]
+fn bad_arguments_are_errors() {
+    assert!(matches!(generate_png_bytes(40, 24, 0, 0.5, (3, 5)), Err(ConfigError::NoOctaves { .. })));
+    assert!(matches!(generate_png_bytes(0, 24, 6, 0.5, (3, 5)), Err(ConfigError::EmptyMap { .. })));
+    assert!(matches!(generate_png_bytes(40, 24, 6, 1.5, (3, 5)), Err(ConfigError::Attenuation(_))));
+    assert!(generate_png_bytes(40, 24, 6, 0., (3, 5)).is_ok());
+}
@@ -0,0 +1,39 @@
+#![cfg(feature = "std")]
+
+use heightmap_gen::{Fade, HeightmapConfig, NoiseKind, NoiseStyle, Normalization, WorleyMode};
+
+#[test]
+fn points_agree_with_the_map() {
+    let base = HeightmapConfig::default().with_size(40, 30).with_origin(-1234, 567);
+    let configs = [
+        base.clone(),
+        base.clone().with_noise(NoiseKind::Simplex).with_style(NoiseStyle::Ridged { squared: true }),
+        base.clone().with_noise(NoiseKind::Worley(WorleyMode::F2)).with_island(Some(2.)),
+        base.clone().with_noise(NoiseKind::Value).with_fade(Fade::Quintic),
+        base.clone().with_warp_strength(8.).with_anisotropy(0.7, 2.5),
+        base.clone().with_octave_rotation(true).with_feature_scale(2., 1.),
+        base.clone().with_tileable(true).with_terraces(6, 0.4),
+        base.clone().with_bump(Some((0.8, 1.5, (0.3, 0.7)))).with_normalization(Normalization::None),
+    ];
+    for config in configs {
+        let map = config.generate();
+        for ((i, j), &v) in map.indexed_iter() {
+            assert_eq!(config.height_at(j as f32, i as f32), v, "pixel ({i}, {j}) of {config:?}");
+        }
+    }
+}
+
+#[test]
+fn points_off_the_map_continue_it() {
+    let config = HeightmapConfig::default().with_size(50, 50).with_perlin_weight(1.);
+    // the map to the left, as seen from this one
+    let left = config.clone().with_origin(-50, 0).generate();
+    for i in 0..50 { for j in 0..50 {
+        let v = config.height_at(j as f32 - 50., i as f32);
+        assert!((v - left[[i, j]]).abs() < 1e-5, "pixel ({i}, {j})");
+    }}
+
+    // and the same tile again past the edge of a wrapping map
+    let tile = config.with_tileable(true);
+    assert!((tile.height_at(7., 3.) - tile.height_at(57., -47.)).abs() < 1e-5);
+}
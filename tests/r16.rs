@@ -0,0 +1,41 @@
+#![cfg(feature = "std")]
+
+use heightmap_gen::{write_r16, write_s16, ByteOrder};
+use ndarray::arr2;
+
+#[test]
+fn r16_round_trip() {
+    let map = arr2(&[[-1., -0.5, 0.], [0.5, 1., 2.]]);
+    type Decode = fn([u8; 2]) -> u16;
+    for (order, decode) in [(ByteOrder::Big, u16::from_be_bytes as Decode), (ByteOrder::Little, u16::from_le_bytes)] {
+        let mut bytes = Vec::new();
+        write_r16(&mut bytes, &map, order, (-1., 1.)).unwrap();
+        assert_eq!(bytes.len(), map.len() * 2);
+
+        let samples: Vec<u16> = bytes.chunks(2).map(|b| decode([b[0], b[1]])).collect();
+        // `-1..1` maps onto the full range, row-major, and out-of-range heights clamp
+        assert_eq!(samples, [0, 16384, 32768, 49151, 65535, 65535]);
+        for (&s, &v) in samples.iter().zip(map.iter()) {
+            let back = s as f32 / 65535. * 2. - 1.;
+            assert!((back - v.clamp(-1., 1.)).abs() < 1e-4);
+        }
+    }
+}
+
+#[test]
+fn s16_keeps_the_sign() {
+    let map = arr2(&[[-1., -0.5, 0.], [0.25, 0.5, 2.]]);
+    type Decode = fn([u8; 2]) -> i16;
+    for (order, decode) in [(ByteOrder::Big, i16::from_be_bytes as Decode), (ByteOrder::Little, i16::from_le_bytes)] {
+        let mut bytes = Vec::new();
+        write_s16(&mut bytes, &map, order, (-1., 1.)).unwrap();
+        let samples: Vec<i16> = bytes.chunks(2).map(|b| decode([b[0], b[1]])).collect();
+        assert_eq!(samples, [-32767, -16384, 0, 8192, 16384, 32767]);
+    }
+
+    // the end of the range further from 0 sets the scale, and 0 stays at 0
+    let mut bytes = Vec::new();
+    write_s16(&mut bytes, &map, ByteOrder::Little, (-0.5, 0.25)).unwrap();
+    let samples: Vec<i16> = bytes.chunks(2).map(|b| i16::from_le_bytes([b[0], b[1]])).collect();
+    assert_eq!(samples, [-32767, -32767, 0, 16384, 16384, 16384]);
+}
@@ -0,0 +1,72 @@
+#![cfg(feature = "std")]
+
+use heightmap_gen::{Craters, HeightmapConfig, Normalization, Pipeline, Stage};
+use ndarray::Array2;
+
+/// A disk of radius 12 around `(20, 15)` in a 40 × 30 map.
+fn disk_mask() -> Array2<bool> {
+    Array2::from_shape_fn([30, 40], |(i, j)| (i as f32 - 15.).hypot(j as f32 - 20.) < 12.)
+}
+
+#[test]
+fn only_the_region_is_generated() {
+    let config = HeightmapConfig::default().with_size(40, 30);
+    let masked = config.clone().with_region_mask(Some(disk_mask()), -0.25).with_grain(0.01).generate();
+    let grained = config.with_grain(0.01).generate();
+
+    for ((i, j), &inside) in disk_mask().indexed_iter() {
+        // grain is still drawn for every pixel, so the inside matches the unmasked map
+        let expected = if inside { grained[[i, j]] } else { -0.25 };
+        assert_eq!(masked[[i, j]], expected, "pixel ({i}, {j})");
+    }
+}
+
+#[test]
+fn whole_map_normalization_sees_only_the_region() {
+    let config = HeightmapConfig::default().with_size(40, 30).with_perlin_weight(1.)
+        .with_normalization(Normalization::MinMax).with_region_mask(Some(disk_mask()), 5.);
+    let map = config.generate();
+    let inside: Vec<f32> = map.iter().zip(&disk_mask()).filter_map(|(&v, &m)| m.then_some(v)).collect();
+    let (lo, hi) = inside.iter().fold((f32::MAX, f32::MIN), |(lo, hi), &v| (lo.min(v), hi.max(v)));
+    assert!((lo + 1.).abs() < 1e-6 && (hi - 1.).abs() < 1e-6, "{lo}..{hi}");
+    assert_eq!(map.iter().filter(|&&v| v == 5.).count(), disk_mask().iter().filter(|&&m| !m).count());
+}
+
+#[test]
+fn strips_and_points_follow_the_mask() {
+    let config = HeightmapConfig::default().with_size(40, 30).with_region_mask(Some(disk_mask()), 0.5);
+    let map = config.generate();
+    let rows: Vec<f32> = config.generate_strips(8).flat_map(|s| s.into_iter()).collect();
+    assert_eq!(rows, map.iter().copied().collect::<Vec<_>>());
+    for ((i, j), &v) in map.indexed_iter() {
+        assert_eq!(config.height_at(j as f32, i as f32), v, "pixel ({i}, {j})");
+    }
+}
+
+#[test]
+fn previews_shrink_the_mask() {
+    let config = HeightmapConfig::default().with_size(40, 30).with_region_mask(Some(disk_mask()), 0.);
+    let preview = config.preview(20);
+    assert_eq!(preview.validate(), Ok(()));
+    let mask = preview.region_mask.unwrap();
+    assert_eq!(mask.dim(), (15, 20));
+    assert!(mask[[7, 10]] && !mask[[0, 0]]);
+}
+
+#[test]
+fn pipeline_stages_keep_the_fill() {
+    let config = HeightmapConfig::default().with_size(40, 30).with_region_mask(Some(disk_mask()), -0.5);
+    let pipeline = Pipeline::cratered(Craters { cell_size: 8., ..Craters::default() })
+        .then(Stage::Flatten { below: Some(0.), above: None, band: 0. })
+        .then(Stage::Stretch);
+    let map = pipeline.run(&config).unwrap();
+
+    let inside: Vec<f32> = map.iter().zip(&disk_mask()).filter_map(|(&v, &m)| m.then_some(v)).collect();
+    let (lo, hi) = inside.iter().fold((f32::MAX, f32::MIN), |(lo, hi), &v| (lo.min(v), hi.max(v)));
+    assert!((lo + 1.).abs() < 1e-6 && (hi - 1.).abs() < 1e-6, "{lo}..{hi}");
+    for ((i, j), &inside) in disk_mask().indexed_iter() {
+        if !inside {
+            assert_eq!(map[[i, j]], -0.5, "pixel ({i}, {j})");
+        }
+    }
+}
@@ -0,0 +1,114 @@
+#![cfg(feature = "std")]
+
+use std::{env, fs, io::Cursor, path::PathBuf};
+
+use exr::prelude::{read, AttributeValue, ReadChannels, ReadLayers, Text};
+use heightmap_gen::{sidecar_path, BitDepth, ByteOrder, HeightmapConfig, OutputFormat, OutputTarget, WorldScale};
+
+/// A path of its own for each test in the temporary directory.
+fn temp_path(name: &str) -> PathBuf {
+    env::temp_dir().join(format!("heightmap-gen-scale-{}-{name}", std::process::id()))
+}
+
+/// The text chunks and 16-bit samples of a grayscale PNG.
+fn decode_png(bytes: &[u8]) -> (Vec<(String, String)>, Vec<u8>) {
+    let mut reader = png::Decoder::new(bytes).read_info().unwrap();
+    let text = reader.info().uncompressed_latin1_text.iter().map(|t| (t.keyword.clone(), t.text.clone())).collect();
+    let mut buf = vec![0; reader.output_buffer_size()];
+    reader.next_frame(&mut buf).unwrap();
+    (text, buf)
+}
+
+#[test]
+fn pngs_carry_the_scale_as_text() {
+    let map = HeightmapConfig::default().with_size(40, 30).generate();
+    let path = temp_path("heights.png");
+    let target = OutputTarget::new(OutputFormat::Png(BitDepth::Sixteen), &path).with_range(-2., 2.);
+    target.write(&map).unwrap();
+    let (text, plain) = decode_png(&fs::read(&path).unwrap());
+    assert!(text.is_empty());
+
+    target.with_scale(Some(WorldScale::new(2.5, 300.)), false).write(&map).unwrap();
+    let (text, samples) = decode_png(&fs::read(&path).unwrap());
+    let expected = [("meters_per_pixel", "2.5"), ("meters_per_height_unit", "300"), ("height_range", "-2 2")];
+    assert_eq!(text, expected.map(|(k, v)| (k.to_string(), v.to_string())));
+    assert_eq!(samples, plain);
+    // the file holds the scale itself
+    assert!(!sidecar_path(&path).exists());
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn exr_images_carry_the_scale_as_attributes() {
+    let map = HeightmapConfig::default().with_size(24, 16).generate();
+    let path = temp_path("heights.exr");
+    OutputTarget::new(OutputFormat::Exr { clamped: false }, &path)
+        .with_scale(Some(WorldScale::new(0.5, 120.)), false)
+        .write(&map)
+        .unwrap();
+
+    let image = read().no_deep_data().largest_resolution_level().all_channels().first_valid_layer().all_attributes()
+        .from_buffered(Cursor::new(fs::read(&path).unwrap()))
+        .unwrap();
+    let attributes = &image.layer_data.attributes.other;
+    assert_eq!(attributes.get(&Text::from("meters_per_pixel")), Some(&AttributeValue::F32(0.5)));
+    assert_eq!(attributes.get(&Text::from("meters_per_height_unit")), Some(&AttributeValue::F32(120.)));
+    // unclamped heights have no range
+    assert_eq!(attributes.get(&Text::from("height_range")), None);
+    assert!(!sidecar_path(&path).exists());
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn other_formats_get_a_sidecar() {
+    let map = HeightmapConfig::default().with_size(24, 16).generate();
+    let scale = Some(WorldScale::new(4., 1000.));
+    let expected = "{\n  \"width\": 24,\n  \"height\": 16,\n  \"meters_per_pixel\": 4,\n  \
+        \"meters_per_height_unit\": 1000,\n  \"height_range\": [-1, 1]\n}\n";
+
+    let path = temp_path("heights.r16");
+    OutputTarget::new(OutputFormat::R16(ByteOrder::Little), &path).with_scale(scale, false).write(&map).unwrap();
+    assert_eq!(sidecar_path(&path), temp_path("heights.r16.json"));
+    assert_eq!(fs::read_to_string(sidecar_path(&path)).unwrap(), expected);
+    fs::remove_file(sidecar_path(&path)).unwrap();
+    fs::remove_file(path).unwrap();
+
+    // and any format can ask for one as well
+    let path = temp_path("forced.png");
+    OutputTarget::new(OutputFormat::Png(BitDepth::Eight), &path).with_scale(scale, true).write(&map).unwrap();
+    assert_eq!(fs::read_to_string(sidecar_path(&path)).unwrap(), expected);
+    fs::remove_file(sidecar_path(&path)).unwrap();
+    fs::remove_file(path).unwrap();
+
+    // raw heights aren't clamped, so they have no range
+    let path = temp_path("heights.raw");
+    OutputTarget::new(OutputFormat::Raw, &path).with_scale(scale, false).write(&map).unwrap();
+    let expected = "{\n  \"width\": 24,\n  \"height\": 16,\n  \"meters_per_pixel\": 4,\n  \
+        \"meters_per_height_unit\": 1000\n}\n";
+    assert_eq!(fs::read_to_string(sidecar_path(&path)).unwrap(), expected);
+    fs::remove_file(sidecar_path(&path)).unwrap();
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn only_heights_take_a_scale() {
+    let map = HeightmapConfig::default().with_size(24, 16).generate();
+    let path = temp_path("normals.png");
+    OutputTarget::new(OutputFormat::Normals(4.), &path).with_scale(Some(WorldScale::new(4., 1000.)), true)
+        .write(&map)
+        .unwrap();
+    assert!(!sidecar_path(&path).exists());
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn smaller_copies_have_larger_pixels() {
+    let scale = WorldScale::new(2., 50.);
+    assert_eq!(scale.resampled(0.25), WorldScale::new(8., 50.));
+}
+
+#[test]
+#[should_panic(expected = "positive and finite")]
+fn scales_must_be_positive() {
+    WorldScale::new(0., 10.);
+}
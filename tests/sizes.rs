@@ -0,0 +1,29 @@
+#![cfg(feature = "std")]
+
+use heightmap_gen::{HeightmapConfig, NoiseKind};
+
+fn check(config: HeightmapConfig) {
+    let map = config.generate();
+    assert_eq!(map.dim(), (config.height, config.width));
+    assert!(map.iter().all(|v| v.is_finite()), "non-finite height in {config:?}");
+}
+
+#[test]
+fn sizes_not_divisible_by_cell_size() {
+    for (width, height) in [(513, 513), (1000, 1000), (1000, 513), (3, 700)] {
+        check(HeightmapConfig::default().with_size(width, height));
+    }
+}
+
+#[test]
+fn fractional_cell_sizes() {
+    for noise in [NoiseKind::Perlin, NoiseKind::Simplex, NoiseKind::Value] {
+        for tileable in [false, true] {
+            check(HeightmapConfig::default()
+                .with_size(517, 301)
+                .with_feature_scale(0.3, 1.7)
+                .with_noise(noise)
+                .with_tileable(tileable));
+        }
+    }
+}
@@ -0,0 +1,81 @@
+#![cfg(feature = "std")]
+
+//! Statistical properties of the noise rather than exact output, so regressions such as a
+//! broken fade curve or gradient distribution show up as what they break. The seed is fixed, so
+//! the measurements are the same on every run.
+
+use std::f32::consts::FRAC_1_SQRT_2;
+
+use heightmap_gen::{Fade, HeightmapConfig, HeightmapStats, NoiseKind, Normalization};
+use ndarray::Array2;
+
+/// Noise alone, without the squircle blend, with cells of at most 16 pixels so a 512 × 512 map
+/// holds plenty of them.
+fn field(noise: NoiseKind) -> HeightmapConfig {
+    HeightmapConfig::default().with_size(512, 512).with_octaves(5).with_perlin_weight(1.).with_noise(noise)
+}
+
+/// Correlation between pixels `lag` apart along the rows and columns together.
+fn correlation(map: &Array2<f32>, lag: usize) -> f32 {
+    let (height, width) = map.dim();
+    let mean = map.mean().unwrap();
+    let var = map.iter().map(|v| (v - mean).powi(2)).sum::<f32>() / map.len() as f32;
+    let (mut sum, mut n) = (0., 0);
+    for i in 0..height { for j in 0..width - lag {
+        sum += (map[[i, j]] - mean) * (map[[i, j + lag]] - mean);
+        n += 1;
+    }}
+    for i in 0..height - lag { for j in 0..width {
+        sum += (map[[i, j]] - mean) * (map[[i + lag, j]] - mean);
+        n += 1;
+    }}
+    sum / n as f32 / var
+}
+
+#[test]
+fn fbm_is_centred_and_bounded() {
+    for noise in [NoiseKind::Perlin, NoiseKind::Simplex, NoiseKind::Value] {
+        let stats = HeightmapStats::of(&field(noise).generate());
+        assert!(stats.mean.abs() < 0.01, "{noise:?}: {stats:?}");
+        // scale-sum keeps the weighted average within one octave's amplitude
+        assert!(-FRAC_1_SQRT_2 <= stats.min && stats.max <= FRAC_1_SQRT_2, "{noise:?}: {stats:?}");
+        assert!((0.1..0.35).contains(&stats.std_dev), "{noise:?}: {stats:?}");
+    }
+}
+
+#[test]
+fn correlation_falls_with_distance() {
+    for noise in [NoiseKind::Perlin, NoiseKind::Simplex, NoiseKind::Value] {
+        let map = field(noise).generate();
+        let near: Vec<f32> = [1, 2, 3, 4, 6, 8].into_iter().map(|lag| correlation(&map, lag)).collect();
+        assert!(near[0] > 0.9, "{noise:?}: {near:?}");
+        assert!(near.windows(2).all(|w| w[1] < w[0]), "{noise:?}: {near:?}");
+        // well past the coarsest cells the pixels are all but independent
+        for lag in [32, 48, 64] {
+            let far = correlation(&map, lag);
+            assert!(far.abs() < 0.15, "{noise:?} at {lag}: {far}");
+        }
+    }
+}
+
+#[test]
+fn fades_smooth_cell_borders() {
+    // mean squared second difference along the rows on the borders of 16 pixel cells, over
+    // that inside them
+    let crease = |fade: Fade| {
+        let map = field(NoiseKind::Perlin).with_size(256, 256).with_finest_level(4).with_fade(fade)
+            .with_normalization(Normalization::None).generate();
+        let (mut border, mut inner) = ((0., 0), (0., 0));
+        for i in 0..256 { for j in 1..255 {
+            let d2 = (map[[i, j - 1]] - 2. * map[[i, j]] + map[[i, j + 1]]).powi(2);
+            let slot = if j % 16 == 0 { &mut border } else { &mut inner };
+            (slot.0, slot.1) = (slot.0 + d2, slot.1 + 1);
+        }}
+        (border.0 / border.1 as f32) / (inner.0 / inner.1 as f32)
+    };
+    // linear interpolation creases every border, smoothstep's curvature carries on across them
+    // and the quintic fade flattens out at them
+    assert!(crease(Fade::Linear) > 10.);
+    assert!((0.5..2.).contains(&crease(Fade::Cubic)));
+    assert!(crease(Fade::Quintic) < 0.2);
+}
@@ -0,0 +1,57 @@
+#![cfg(feature = "std")]
+
+use heightmap_gen::{contact_sheet, ColorRamp, ConfigError, HeightmapConfig, SweepAxis, SweepParam};
+use ndarray::s;
+
+#[test]
+fn linspace_includes_both_ends() {
+    assert_eq!(SweepAxis::linspace(SweepParam::Attenuation, 0.4, 0.9, 6).values.len(), 6);
+    let axis = SweepAxis::linspace(SweepParam::Octaves, 4., 8., 3);
+    assert_eq!(axis.values, [4., 6., 8.]);
+    assert_eq!(SweepAxis::linspace(SweepParam::Gain, 2., 5., 1).values, [2.]);
+    assert_eq!(SweepParam::Octaves.apply(&HeightmapConfig::default(), 5.6).octaves, 6);
+}
+
+#[test]
+fn thumbnails_are_previews_of_each_setting() {
+    let config = HeightmapConfig::default().with_size(256, 192);
+    let columns = SweepAxis::new(SweepParam::Attenuation, vec![0.4, 0.8]);
+    let rows = SweepAxis::new(SweepParam::Octaves, vec![5., 6., 7.]);
+    let ramp = ColorRamp::terrain();
+    let sheet = contact_sheet(&config, &columns, &rows, 32, &ramp).unwrap();
+
+    let (h, w) = (24, 32);
+    assert!(sheet.nrows() > 3 * h && sheet.ncols() > 2 * w);
+    // thumbnails fill the bottom right corner, a gap apart and with a gap after the last
+    let (top, left) = (sheet.nrows() - 3 * (h + 4), sheet.ncols() - 2 * (w + 4));
+    for (r, &octaves) in rows.values.iter().enumerate() {
+        for (c, &attenuation) in columns.values.iter().enumerate() {
+            let thumb = config.clone().with_octaves(octaves as usize).with_attenuation(attenuation).preview(32);
+            let (i, j) = (top + r * (h + 4), left + c * (w + 4));
+            assert_eq!(sheet.slice(s![i..i + h, j..j + w]), ramp.apply(&thumb.generate()));
+        }
+    }
+    // the labels are drawn in the margins
+    assert!(sheet.slice(s![..top, left..]).iter().any(|&p| p == [224, 224, 224]));
+    assert!(sheet.slice(s![top.., ..left]).iter().any(|&p| p == [224, 224, 224]));
+}
+
+#[test]
+fn invalid_settings_fail() {
+    let columns = SweepAxis::new(SweepParam::Attenuation, vec![0.5, 1.5]);
+    let rows = SweepAxis::new(SweepParam::Gain, vec![1.]);
+    let res = contact_sheet(&HeightmapConfig::default(), &columns, &rows, 16, &ColorRamp::grayscale());
+    assert!(matches!(res, Err(ConfigError::Attenuation(a)) if a == 1.5));
+}
+
+#[test]
+fn labels_show_the_values_set() {
+    let config = HeightmapConfig::default().with_size(64, 64);
+    let columns = SweepAxis::new(SweepParam::Gain, vec![1.]);
+    let ramp = ColorRamp::grayscale();
+    // octaves are rounded, and so is the label: "4" rather than "3.5"
+    let rounded = contact_sheet(&config, &columns, &SweepAxis::new(SweepParam::Octaves, vec![3.5]), 16, &ramp);
+    let whole = contact_sheet(&config, &columns, &SweepAxis::new(SweepParam::Octaves, vec![4.]), 16, &ramp);
+    assert_eq!(rounded.unwrap(), whole.unwrap());
+    assert_eq!(SweepParam::ALL.map(|p| p.get(&p.apply(&config, 0.5))), [1., 0.5, 0.5, 0.5, 0.5, 0.5]);
+}
@@ -0,0 +1,65 @@
+#![cfg(feature = "std")]
+
+use heightmap_gen::{GradientSource, HeightmapConfig, NoiseKind, WorleyMode};
+
+/// Generates the tile at `origin`, one pixel wider and taller than `size` so it overlaps its
+/// neighbours.
+fn tile(config: &HeightmapConfig, size: usize, origin: (i64, i64)) -> ndarray::Array2<f32> {
+    config.clone().with_size(size + 1, size + 1).with_origin(origin.0, origin.1).generate()
+}
+
+#[test]
+fn neighbouring_tiles_share_edges() {
+    let size = 100;
+    for noise in [NoiseKind::Perlin, NoiseKind::Worley(WorleyMode::F1)] {
+        let config = HeightmapConfig::default()
+            .with_noise(noise)
+            .with_gradients(GradientSource::Hashed)
+            .with_perlin_weight(1.)
+            .with_feature_scale(1.3, 0.7);
+        let (a, b, c) = (tile(&config, size, (0, 0)), tile(&config, size, (size as i64, 0)),
+            tile(&config, size, (-5000, 3 * size as i64)));
+        let d = tile(&config, size, (-5000, 4 * size as i64));
+
+        for k in 0..=size {
+            assert!((a[[k, size]] - b[[k, 0]]).abs() < 1e-4, "{noise:?} row {k}");
+            assert!((c[[size, k]] - d[[0, k]]).abs() < 1e-4, "{noise:?} column {k}");
+        }
+    }
+}
+
+#[test]
+fn resizing_keeps_the_overlap() {
+    let config = HeightmapConfig::default().with_perlin_weight(1.);
+    let small = config.clone().with_size(96, 64).generate();
+    let large = config.with_size(160, 200).generate();
+
+    for ((i, j), &v) in small.indexed_iter() {
+        assert!((v - large[[i, j]]).abs() < 1e-6, "pixel ({i}, {j})");
+    }
+}
+
+#[test]
+fn stretched_tiles_share_edges() {
+    let size = 100;
+    let config = HeightmapConfig::default().with_perlin_weight(1.).with_anisotropy(0.5, 3.);
+    let (a, b) = (tile(&config, size, (0, 0)), tile(&config, size, (size as i64, 0)));
+    for k in 0..=size {
+        assert!((a[[k, size]] - b[[k, 0]]).abs() < 1e-4, "row {k}");
+    }
+
+    // at an aspect of 1 nothing is stretched
+    let plain = HeightmapConfig::default().with_size(64, 48);
+    assert_eq!(plain.clone().with_anisotropy(1.2, 1.).generate(), plain.generate());
+}
+
+#[test]
+fn rotated_tiles_share_edges() {
+    let size = 100;
+    let config = HeightmapConfig::default().with_perlin_weight(1.).with_octave_rotation(true);
+    let (a, b) = (tile(&config, size, (0, 0)), tile(&config, size, (0, size as i64)));
+    for k in 0..=size {
+        assert!((a[[size, k]] - b[[0, k]]).abs() < 1e-4, "column {k}");
+    }
+    assert_ne!(a, tile(&config.with_octave_rotation(false), size, (0, 0)));
+}
@@ -0,0 +1,51 @@
+#![cfg(feature = "std")]
+
+use heightmap_gen::{ConfigError, HeightmapConfig};
+use ndarray::Array2;
+
+#[test]
+fn default_is_valid() {
+    assert_eq!(HeightmapConfig::default().validate(), Ok(()));
+}
+
+#[test]
+fn rejects_degenerate_settings() {
+    let base = HeightmapConfig::default().with_size(16, 16);
+    let cases = [
+        (base.clone().with_octaves(0), ConfigError::NoOctaves { octaves: 0, finest_level: 0 }),
+        (base.clone().with_size(0, 16), ConfigError::EmptyMap { width: 0, height: 16 }),
+        (base.clone().with_attenuation(1.5), ConfigError::Attenuation(1.5)),
+        (base.clone().with_lacunarity(0.5), ConfigError::Lacunarity(0.5)),
+        (base.clone().with_lacunarity(1.), ConfigError::Lacunarity(1.)),
+        (base.clone().with_octave_weights(Some(vec![0., 0.])), ConfigError::ZeroWeights),
+        (base.clone().with_tileable(true).with_anisotropy(0.3, 2.), ConfigError::AnisotropicWrap),
+        (base.clone().with_wrap(true, false).with_octave_rotation(true), ConfigError::RotatedWrap),
+        (base.clone().with_bump(Some((1., -2., (0.5, 0.5)))), ConfigError::BumpPower(-2.)),
+        (base.clone().with_bump(Some((1., 2., (f32::INFINITY, 0.5)))), ConfigError::NotFinite("bump")),
+        (base.clone().with_region_mask(Some(Array2::from_elem([16, 8], true)), 0.),
+            ConfigError::RegionMask { width: 8, height: 16 }),
+        (base.with_gain_bias(f32::NAN, 0.), ConfigError::NotFinite("gain")),
+    ];
+    for (config, expected) in cases {
+        assert_eq!(config.validate(), Err(expected));
+    }
+}
+
+#[test]
+fn rejects_grids_too_fine_for_the_map() {
+    let base = HeightmapConfig::default().with_size(8, 8);
+    // cells so small their counts overflow, and so small the grid wouldn't fit in memory
+    let overflowing = base.clone().with_feature_scale(1e-30, 1.);
+    assert_eq!(overflowing.validate(), Err(ConfigError::GridSize { level: 7 }));
+    // the coarsest level too fine at this scale is the one reported
+    let huge = base.clone().with_size(64, 64).with_feature_scale(0.001, 0.001).with_octaves(12);
+    assert_eq!(huge.validate(), Err(ConfigError::GridSize { level: 7 }));
+    assert_eq!(huge.with_finest_level(9).validate(), Ok(()));
+    assert_eq!(base.with_feature_scale(0.5, 0.5).validate(), Ok(()));
+}
+
+#[test]
+#[should_panic(expected = "no octaves")]
+fn generate_checks_first() {
+    HeightmapConfig::default().with_size(16, 16).with_octaves(0).generate();
+}
@@ -0,0 +1,28 @@
+#![cfg(feature = "std")]
+
+use heightmap_gen::{offset_colors, HeightmapConfig};
+
+#[test]
+fn offsets_move_the_sampled_point() {
+    let config = HeightmapConfig::default().with_size(48, 40).with_perlin_weight(1.);
+    assert!(config.warp_offsets().iter().all(|&o| o == (0., 0.)));
+
+    let warped = config.with_warp_strength(6.);
+    let offsets = warped.warp_offsets();
+    assert!(offsets.iter().all(|&(x, y)| x.abs() <= 6. && y.abs() <= 6.));
+    assert!(offsets.iter().any(|&(x, y)| x.abs() > 1. && y.abs() > 1.));
+    // the same field warps the map, so sampling the noise alone at the warped point reproduces it
+    let map = warped.generate();
+    let unwarped = warped.clone().with_warp_strength(0.);
+    for &(i, j) in &[(10, 12), (25, 30), (33, 17)] {
+        let (x, y) = offsets[[i, j]];
+        let expected = unwarped.height_at(j as f32 + x, i as f32 + y);
+        assert!((map[[i, j]] - expected).abs() < 1e-4, "{} vs {expected}", map[[i, j]]);
+    }
+
+    let colors = offset_colors(&offsets, 6.);
+    for (&(x, y), &[r, g, b]) in offsets.iter().zip(&colors) {
+        let encode = |d: f32| ((d / 12. + 0.5) * 255.).round() as u8;
+        assert_eq!((r, g, b), (encode(x), encode(y), 0));
+    }
+}
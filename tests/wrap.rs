@@ -0,0 +1,86 @@
+#![cfg(feature = "std")]
+
+use heightmap_gen::{normal_map, slope_map, thermal_erode, HeightmapConfig};
+use ndarray::{concatenate, s, Array2, ArrayView1, Axis};
+
+/// Largest height difference between two rows or columns.
+fn step(a: ArrayView1<f32>, b: ArrayView1<f32>) -> f32 {
+    a.iter().zip(b).map(|(a, b)| (a - b).abs()).fold(0., f32::max)
+}
+
+/// Largest step between neighbouring columns inside `map`.
+fn interior_step(map: &Array2<f32>) -> f32 {
+    (1..map.ncols()).map(|j| step(map.column(j - 1), map.column(j))).fold(0., f32::max)
+}
+
+#[test]
+fn wrap_x_only() {
+    let map = HeightmapConfig::default()
+        .with_size(300, 200)
+        .with_perlin_weight(1.)
+        .with_wrap(true, false)
+        .generate();
+    let (height, width) = map.dim();
+
+    // crossing from the last column to the first is no bigger a step than any inside the map
+    let seam = step(map.column(width - 1), map.column(0));
+    assert!(seam <= interior_step(&map) * 1.5, "seam step {seam}");
+
+    // while the top and bottom rows have nothing to do with each other
+    let top_bottom = step(map.row(height - 1), map.row(0));
+    let interior = step(map.row(0), map.row(1));
+    assert!(top_bottom > interior * 4., "top/bottom step {top_bottom}");
+}
+
+#[test]
+fn derived_maps_tile() {
+    let map = HeightmapConfig::default().with_size(128, 96).with_perlin_weight(1.).with_tileable(true).generate();
+    // the same tile starting halfway across and down
+    let roll = |m: &Array2<f32>| {
+        let m = concatenate![Axis(1), m.slice(s![.., 64..]), m.slice(s![.., ..64])];
+        concatenate![Axis(0), m.slice(s![48.., ..]), m.slice(s![..48, ..])]
+    };
+    let rolled = roll(&map);
+
+    // so edge pixels see the same neighbours as the middle of the rolled map does
+    let (normals, rolled_normals) = (normal_map(&map, 20., (true, true)), normal_map(&rolled, 20., (true, true)));
+    let (slopes, rolled_slopes) = (slope_map(&map, (true, true)), slope_map(&rolled, (true, true)));
+    for i in 0..96 { for j in 0..128 {
+        let (ri, rj) = ((i + 48) % 96, (j + 64) % 128);
+        assert_eq!(normals[[ri, rj]], rolled_normals[[i, j]], "normal at ({ri}, {rj})");
+        assert!((slopes[[ri, rj]] - rolled_slopes[[i, j]]).abs() < 1e-6, "slope at ({ri}, {rj})");
+    }}
+}
+
+#[test]
+fn disk_has_no_seam() {
+    let map = HeightmapConfig::default().with_size(256, 256).with_perlin_weight(1.).generate_disk(-2.);
+
+    assert_eq!(map[[0, 0]], -2.);
+    assert!(map[[128, 128]] > -1.5);
+
+    // the angle comes back to zero between these two rows, right of the centre
+    let (above, below) = (map.slice(s![127, 140..250]), map.slice(s![128, 140..250]));
+    let seam = step(above, below);
+    // and the same distance apart below the centre, where it doesn't
+    let interior = step(map.slice(s![140..250, 127]), map.slice(s![140..250, 128]));
+    assert!(seam <= interior * 1.5, "seam step {seam}, interior {interior}");
+}
+
+#[test]
+fn thermal_erosion_tiles() {
+    let map = HeightmapConfig::default().with_size(64, 48).with_perlin_weight(1.).with_tileable(true).generate();
+    let roll = |m: &Array2<f32>| {
+        let m = concatenate![Axis(1), m.slice(s![.., 32..]), m.slice(s![.., ..32])];
+        concatenate![Axis(0), m.slice(s![24.., ..]), m.slice(s![..24, ..])]
+    };
+
+    // eroding the rolled tile moves the same material as eroding the tile, edges included
+    let (mut eroded, mut rolled) = (map.clone(), roll(&map));
+    thermal_erode(&mut eroded, 20, 0.01, (true, true));
+    thermal_erode(&mut rolled, 20, 0.01, (true, true));
+    assert_ne!(eroded, map);
+    for ((i, j), &v) in roll(&eroded).indexed_iter() {
+        assert!((v - rolled[[i, j]]).abs() < 1e-5, "height at ({i}, {j})");
+    }
+}